@@ -16,8 +16,12 @@ struct Mesh2d {
     flags: u32,
 };
 
-struct Elapsed {
-  seconds: f32,
+struct CycleSettings {
+    speed: f32,
+    frequency: f32,
+    angle: f32,
+    saturation: f32,
+    value: f32,
 };
 
 @group(0) @binding(0)
@@ -29,6 +33,14 @@ var<uniform> elapsed_seconds: f32;
 var texture: texture_2d<f32>;
 @group(1) @binding(2)
 var texture_sampler: sampler;
+@group(1) @binding(3)
+var<uniform> enabled: u32;
+@group(1) @binding(4)
+var<uniform> settings: CycleSettings;
+@group(1) @binding(5)
+var mask: texture_2d<f32>;
+@group(1) @binding(6)
+var mask_sampler: sampler;
 
 @group(2) @binding(0)
 var<uniform> mesh: Mesh2d;
@@ -69,12 +81,21 @@ fn hsv2rgb(c: vec4<f32>) -> vec4<f32> {
 fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
     var tex_color: vec4<f32> = textureSample(texture, texture_sampler, in.uv);
 
+    if (enabled == 0u) {
+        return tex_color;
+    }
+
     var color_hsv = rgb2hsv(tex_color);
 
+    // Distance along the direction of the bands, in pixels.
     var dims = textureDimensions(texture);
-    var pixmod = (in.uv.x * f32(dims.x) + in.uv.y * f32(dims.y)) / 100.;
+    var pixel = in.uv * vec2<f32>(f32(dims.x), f32(dims.y));
+    var distance = dot(pixel, vec2<f32>(cos(settings.angle), sin(settings.angle)));
 
-    color_hsv.x = color_hsv.x + elapsed_seconds - pixmod;
+    color_hsv.x = color_hsv.x + elapsed_seconds * settings.speed - distance * settings.frequency;
+    color_hsv.y = clamp(color_hsv.y * settings.saturation, 0., 1.);
+    color_hsv.z = clamp(color_hsv.z * settings.value, 0., 1.);
 
-    return hsv2rgb(color_hsv);
+    var amount = textureSample(mask, mask_sampler, in.uv).r;
+    return mix(tex_color, hsv2rgb(color_hsv), amount);
 }
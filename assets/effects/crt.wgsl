@@ -0,0 +1,62 @@
+struct Crt {
+    resolution: vec2<f32>,
+    scanline_darkness: f32,
+    curvature: f32,
+    bloom: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> crt: Crt;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+let PI: f32 = 3.141592654;
+
+// Bend the picture outwards from its centre, like the glass of a tube.
+fn curve(uv: vec2<f32>) -> vec2<f32> {
+    var centred = uv * 2. - 1.;
+    var bent = centred * (1. + crt.curvature * dot(centred.yx, centred.yx));
+    return bent * 0.5 + 0.5;
+}
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var uv = curve(in.uv);
+    // Sampling has to happen in uniform control flow, so the black border around the curved
+    // picture is picked at the end rather than returned early.
+    var outside = uv.x < 0. || uv.x > 1. || uv.y < 0. || uv.y > 1.;
+
+    var color = textureSample(texture, texture_sampler, uv).rgb;
+
+    // Glow: add the brightest parts of the neighbouring pixels.
+    var pixel = 1. / crt.resolution;
+    var glow = vec3<f32>(0., 0., 0.);
+    for (var x = -1; x <= 1; x = x + 1) {
+        for (var y = -1; y <= 1; y = y + 1) {
+            var offset = vec2<f32>(f32(x), f32(y)) * pixel;
+            var neighbour = textureSample(texture, texture_sampler, uv + offset).rgb;
+            glow = glow + max(neighbour - vec3<f32>(0.5, 0.5, 0.5), vec3<f32>(0., 0., 0.));
+        }
+    }
+    color = color + glow / 9. * crt.bloom * 2.;
+
+    // Scanlines: darkest between rows of the display's pixels, brightest through their middles.
+    var row = uv.y * crt.resolution.y;
+    var scanline = 1. - crt.scanline_darkness * (1. - abs(sin(row * PI)));
+    color = color * scanline;
+
+    color = select(min(color, vec3<f32>(1., 1., 1.)), vec3<f32>(0., 0., 0.), outside);
+    return vec4<f32>(color, 1.);
+}
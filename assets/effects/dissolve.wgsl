@@ -0,0 +1,45 @@
+struct Dissolve {
+    edge_color: vec4<f32>,
+    amount: f32,
+    edge_width: f32,
+    block_size: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> dissolve: Dissolve;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+// A repeatable pseudo-random number from 0 to 1 for each block.
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var color = textureSample(texture, texture_sampler, in.uv);
+
+    var dims = textureDimensions(texture);
+    var block = floor(in.uv * vec2<f32>(f32(dims.x), f32(dims.y)) / dissolve.block_size);
+    var noise = hash(block);
+
+    if (noise < dissolve.amount) {
+        return vec4<f32>(0., 0., 0., 0.);
+    }
+    if (dissolve.amount > 0. && noise < dissolve.amount + dissolve.edge_width) {
+        return vec4<f32>(dissolve.edge_color.rgb, dissolve.edge_color.a * color.a);
+    }
+    return color;
+}
@@ -0,0 +1,28 @@
+struct Flash {
+    color: vec4<f32>,
+    amount: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> flash: Flash;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var color = textureSample(texture, texture_sampler, in.uv);
+    var rgb = mix(color.rgb, flash.color.rgb, clamp(flash.amount * flash.color.a, 0., 1.));
+    return vec4<f32>(rgb, color.a);
+}
@@ -0,0 +1,41 @@
+struct Outline {
+    color: vec4<f32>,
+    thickness: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> outline: Outline;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var color = textureSample(texture, texture_sampler, in.uv);
+
+    // Transparent pixels next to opaque ones, in any of eight directions, are outline.
+    var dims = textureDimensions(texture);
+    var texel = outline.thickness / vec2<f32>(f32(dims.x), f32(dims.y));
+    var coverage = 0.;
+    for (var x = -1; x <= 1; x = x + 1) {
+        for (var y = -1; y <= 1; y = y + 1) {
+            var offset = vec2<f32>(f32(x), f32(y)) * texel;
+            coverage = max(coverage, textureSample(texture, texture_sampler, in.uv + offset).a);
+        }
+    }
+
+    // Picked after all the sampling, which has to happen in uniform control flow.
+    var is_outline = color.a < 0.5 && coverage >= 0.5;
+    return select(color, outline.color, is_outline);
+}
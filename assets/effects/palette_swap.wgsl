@@ -0,0 +1,34 @@
+struct PaletteSwap {
+    old_colors: array<vec4<f32>, 8>,
+    new_colors: array<vec4<f32>, 8>,
+    count: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> swap: PaletteSwap;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var color = textureSample(texture, texture_sampler, in.uv);
+
+    for (var i = 0u; i < swap.count; i = i + 1u) {
+        if (distance(color.rgb, swap.old_colors[i].rgb) < 0.01) {
+            return vec4<f32>(swap.new_colors[i].rgb, swap.new_colors[i].a * color.a);
+        }
+    }
+    return color;
+}
@@ -0,0 +1,32 @@
+struct Scanline {
+    spacing: f32,
+    darkness: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> scanline: Scanline;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var color = textureSample(texture, texture_sampler, in.uv);
+
+    var row = floor(in.uv.y * f32(textureDimensions(texture).y));
+    if (row % scanline.spacing < 1.) {
+        return vec4<f32>(color.rgb * (1. - scanline.darkness), color.a);
+    }
+    return color;
+}
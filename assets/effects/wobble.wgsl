@@ -0,0 +1,41 @@
+struct Wobble {
+    amplitude: f32,
+    frequency: f32,
+    speed: f32,
+    elapsed_seconds: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> wobble: Wobble;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+let TAU: f32 = 6.283185307;
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var dims = textureDimensions(texture);
+
+    // Move whole rows of pixels, so the image stays crisp.
+    var row = floor(in.uv.y * f32(dims.y)) / f32(dims.y);
+    var phase = (row * wobble.frequency - wobble.elapsed_seconds * wobble.speed) * TAU;
+    var shift = round(sin(phase) * wobble.amplitude) / f32(dims.x);
+
+    var uv = vec2<f32>(in.uv.x + shift, in.uv.y);
+    // Sample before deciding, as sampling has to happen in uniform control flow.
+    var color = textureSample(texture, texture_sampler, uv);
+    var outside = uv.x < 0. || uv.x > 1.;
+    return select(color, vec4<f32>(0., 0., 0., 0.), outside);
+}
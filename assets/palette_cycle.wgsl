@@ -0,0 +1,50 @@
+struct PaletteRange {
+    start: u32,
+    end: u32,
+    speed: f32,
+    offset: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> elapsed_seconds: f32;
+@group(1) @binding(1)
+var indices: texture_2d<f32>;
+@group(1) @binding(2)
+var palette: texture_2d<f32>;
+@group(1) @binding(3)
+var<uniform> ranges: array<PaletteRange, 8>;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    // Load rather than sample, so indices are never blended with their neighbours.
+    var dims = textureDimensions(indices);
+    var coords = min(vec2<i32>(in.uv * vec2<f32>(dims)), dims - vec2<i32>(1, 1));
+    var texel = textureLoad(indices, coords, 0);
+    var index = i32(round(texel.r * 255.));
+
+    // Rotate the index through the first range it falls in.
+    for (var i = 0; i < 8; i = i + 1) {
+        var cycle_range = ranges[i];
+        var start = i32(cycle_range.start);
+        var count = i32(cycle_range.end) - start + 1;
+        if (count > 1 && index >= start && index < start + count) {
+            var steps = i32(floor(elapsed_seconds * cycle_range.speed + cycle_range.offset));
+            index = start + ((index - start - steps) % count + count) % count;
+            break;
+        }
+    }
+
+    var palette_width = textureDimensions(palette).x;
+    var color = textureLoad(palette, vec2<i32>(index % palette_width, index / palette_width), 0);
+    return vec4<f32>(color.rgb, color.a * texel.a);
+}
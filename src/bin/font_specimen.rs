@@ -0,0 +1,60 @@
+// Render a font specimen sheet to a PNG, without opening a window:
+// every character in the font's char map, followed by a sample paragraph.
+//
+// Usage: font_specimen <descriptor> <output.png> [sample text]
+
+use bevy::prelude::*;
+use std::{env, path::Path, process};
+
+use pizzarust::font::{render_text_image, BitmapFont, BitmapText, TextImages};
+
+
+const SPECIMEN_COLUMNS: usize = 16;
+const SPECIMEN_PADDING: f32 = 4.;
+const SAMPLE_TEXT: &str = "The quick brown fox jumps over the lazy dog. 0123456789";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <descriptor> <output.png> [sample text]", args[0]);
+        process::exit(1);
+    }
+
+    if let Err(error) = run(Path::new(&args[1]), Path::new(&args[2]),
+        args.get(3).map_or(SAMPLE_TEXT, |s| s.as_str())) {
+        eprintln!("Error: {:#}", error);
+        process::exit(1);
+    }
+}
+
+fn run(descriptor_path: &Path, output_path: &Path, sample: &str) -> Result<(), anyhow::Error> {
+    let font = BitmapFont::from_file(descriptor_path)?;
+    let info = font.info();
+
+    // Lay the mapped characters out in rows, escaping brackets so they aren't read as markup.
+    let chars: Vec<char> = info.char_map.chars().iter().map(|&(c, _)| c).collect();
+    let rows: Vec<String> = chars.chunks(SPECIMEN_COLUMNS)
+        .map(|row| row.iter().collect::<String>().replace('[', "[["))
+        .collect();
+
+    let text = BitmapText {
+        text: format!("{}\n\n{}", rows.join("\n"), sample.replace('[', "[[")),
+        font: info.name.clone(),
+        box_size: Vec2::new(
+            SPECIMEN_COLUMNS as f32 * (info.tile_size.x + info.letter_spacing)
+                + SPECIMEN_PADDING * 2.,
+            0.,
+        ),
+        padding: SPECIMEN_PADDING,
+        background_color: Color::rgb(0.1, 0.1, 0.2),
+        ..Default::default()
+    };
+    println!("{}: {} characters mapped.", info.name, chars.len());
+
+    // Only this font is passed in, so the sheet shows its own glyphs and none of its fallbacks.
+    let image = render_text_image(&text, &[font], &TextImages::default());
+    image.save(output_path)?;
+
+    println!("Wrote {}.", output_path.display());
+    Ok(())
+}
@@ -1,15 +1,22 @@
 use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::f32::consts::TAU;
 
+use super::clock::GameClock;
+use super::font::{BitmapText, Glyph};
+
 
 pub struct BouncePlugin;
 
 impl Plugin for BouncePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(bounce);
+        app
+            .add_system(bounce)
+            .add_system(animate_glyphs);
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum EffectType {
     Bounce,
     HorizontalWave,
@@ -17,6 +24,15 @@ pub enum EffectType {
 impl Default for EffectType {
     fn default() -> Self { EffectType::Bounce }
 }
+impl EffectType {
+    // The offset at the given phase of the effect's period, from 0 to 1.
+    fn offset(&self, phase: f32, distance: f32) -> Vec2 {
+        match self {
+            EffectType::Bounce => Vec2::new(0., (phase * TAU / 2.).sin().abs() * distance),
+            EffectType::HorizontalWave => Vec2::new((phase * TAU).sin() * distance, 0.),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct BounceEffect {
@@ -31,18 +47,46 @@ pub struct Bounce {
     pub effects: Vec<BounceEffect>,
 }
 
+pub enum GlyphEffectType {
+    // One of the whole-text effects, with each character a step further along in its period.
+    Wave(EffectType),
+    // Reveal one character per period, restarting whenever the text changes.
+    Typewriter,
+    // Jump each character to a new random offset once per period.
+    Shake,
+    // Tint each character with a hue that cycles once per period.
+    ColorCycle,
+}
+impl Default for GlyphEffectType {
+    fn default() -> Self { GlyphEffectType::Wave(EffectType::Bounce) }
+}
+
+#[derive(Default)]
+pub struct GlyphEffect {
+    pub effect_type: GlyphEffectType,
+    pub distance: f32,
+    pub period: f64,
+    // How far each character's phase is ahead of the previous one's, as a fraction of the period.
+    pub phase_step: f32,
+}
+
+// Per-character effects for a BitmapText rendered with TextRenderMode::Glyphs.
+#[derive(Component, Default)]
+pub struct GlyphEffects {
+    pub effects: Vec<GlyphEffect>,
+    // Time since the text last changed.
+    pub elapsed: f64,
+}
+
 fn bounce(
     mut objects: Query<(&mut Bounce, &mut Transform)>,
-    time: Res<Time>,
+    clock: Res<GameClock>,
 ) {
     for (mut bounce, mut transform) in objects.iter_mut() {
         for mut effect in bounce.effects.iter_mut() {
-            let phase = (time.seconds_since_startup() / effect.period) as f32 % 1.;
+            let phase = (clock.seconds() / effect.period) as f32 % 1.;
 
-            let offset = match effect.effect_type {
-                EffectType::Bounce => Vec2::new(0., (phase * TAU / 2.).sin().abs() * effect.distance),
-                EffectType::HorizontalWave => Vec2::new((phase * TAU).sin() * effect.distance, 0.),
-            };
+            let offset = effect.effect_type.offset(phase, effect.distance);
 
             transform.translation = Vec3::new(
                 transform.translation.x - effect.current_offset.x + offset.x,
@@ -53,3 +97,59 @@ fn bounce(
         }
     }
 }
+
+fn animate_glyphs(
+    mut texts: Query<(&mut GlyphEffects, &Children, ChangeTrackers<BitmapText>)>,
+    mut glyphs: Query<(&Glyph, &mut Transform, &mut TextureAtlasSprite, &mut Visibility)>,
+    clock: Res<GameClock>,
+) {
+    for (mut glyph_effects, children, text_tracker) in texts.iter_mut() {
+        if text_tracker.is_changed() {
+            glyph_effects.elapsed = 0.;
+        } else {
+            glyph_effects.elapsed += clock.delta().as_secs_f64();
+        }
+
+        for child in children.iter() {
+            if let Ok((glyph, mut transform, mut sprite, mut visibility)) = glyphs.get_mut(*child) {
+                let mut offset = Vec2::ZERO;
+                let mut color = glyph.color;
+                let mut is_visible = true;
+
+                for effect in glyph_effects.effects.iter() {
+                    let periods = clock.seconds() / effect.period;
+                    let phase = (periods as f32 + glyph.char_index as f32 * effect.phase_step)
+                        .rem_euclid(1.);
+
+                    match effect.effect_type {
+                        GlyphEffectType::Wave(effect_type) => {
+                            offset += effect_type.offset(phase, effect.distance);
+                        }
+                        GlyphEffectType::Typewriter => {
+                            is_visible &= glyph.char_index as f64
+                                <= glyph_effects.elapsed / effect.period;
+                        }
+                        GlyphEffectType::Shake => {
+                            // Seed from the character and period, so each one holds its offset
+                            // for the whole period and differs from its neighbours.
+                            let seed = (periods as u64).wrapping_mul(1024)
+                                + glyph.char_index as u64;
+                            let mut rng = StdRng::seed_from_u64(seed);
+                            offset += Vec2::new(
+                                rng.gen_range(-effect.distance..=effect.distance),
+                                rng.gen_range(-effect.distance..=effect.distance),
+                            ).round();
+                        }
+                        GlyphEffectType::ColorCycle => {
+                            color = Color::hsl(phase * 360., 1., 0.7);
+                        }
+                    }
+                }
+
+                transform.translation = glyph.origin + offset.extend(0.);
+                sprite.color = color;
+                visibility.is_visible = is_visible;
+            }
+        }
+    }
+}
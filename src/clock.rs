@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(GameClock::default())
+            // Time is updated in the First stage, so it is ready to read here.
+            .add_system_to_stage(CoreStage::PreUpdate, tick_clock);
+    }
+}
+
+// Gameplay time, which stands still while the game is paused.
+// Systems that animate the game world should read this instead of Time.
+#[derive(Default)]
+pub struct GameClock {
+    pub paused: bool,
+    elapsed: f64,
+    delta: Duration,
+}
+impl GameClock {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.elapsed
+    }
+}
+
+fn tick_clock(
+    mut clock: ResMut<GameClock>,
+    time: Res<Time>,
+) {
+    if clock.paused {
+        clock.delta = Duration::ZERO;
+    } else {
+        clock.delta = time.delta();
+        clock.elapsed += time.delta_seconds_f64();
+    }
+}
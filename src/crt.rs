@@ -0,0 +1,199 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_resource::*,
+        view::RenderLayers,
+    },
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
+    window::WindowId,
+};
+
+use super::MainCamera;
+use super::display::Display;
+
+
+// The layer the CRT screen is drawn on, so the world camera doesn't see it
+// and the screen camera doesn't see the world.
+const CRT_LAYER: u8 = 1;
+
+// Draws the game like an old television: the world is rendered to an image at the display's
+// resolution, then drawn to the window through a shader that adds scanlines,
+// a curved screen and glow as it scales the image up.
+pub struct CrtPlugin;
+
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(CrtSettings::default())
+            .add_plugin(Material2dPlugin::<CrtMaterial>::default())
+            .add_event::<ToggleCrtEvent>()
+            .add_system(toggle_crt)
+            .add_system(apply_crt.after(toggle_crt));
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CrtSettings {
+    pub enabled: bool,
+    // How much the gaps between rows of pixels darken the picture, from 0 to 1.
+    pub scanline_darkness: f32,
+    // How far the corners of the picture bend in, as a fraction of its size.
+    pub curvature: f32,
+    // How much bright pixels glow into their neighbours.
+    pub bloom: f32,
+}
+impl Default for CrtSettings {
+    fn default() -> Self {
+        CrtSettings {
+            enabled: false,
+            scanline_darkness: 0.4,
+            curvature: 0.03,
+            bloom: 0.3,
+        }
+    }
+}
+
+pub struct ToggleCrtEvent;
+
+// The camera and quad that draw the rendered world to the window.
+#[derive(Component)]
+struct CrtScreen;
+
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "c41f2b7e-8d95-4a36-b0e2-5f7a9c3d1e68"]
+#[uniform(0, CrtUniform)]
+pub struct CrtMaterial {
+    pub resolution: Vec2,
+    pub settings: CrtSettings,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Handle<Image>,
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct CrtUniform {
+    resolution: Vec2,
+    scanline_darkness: f32,
+    curvature: f32,
+    bloom: f32,
+}
+impl AsBindGroupShaderType<CrtUniform> for CrtMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> CrtUniform {
+        CrtUniform {
+            resolution: self.resolution,
+            scanline_darkness: self.settings.scanline_darkness,
+            curvature: self.settings.curvature,
+            bloom: self.settings.bloom,
+        }
+    }
+}
+
+impl Material2d for CrtMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/crt.wgsl".into()
+    }
+}
+
+fn toggle_crt(
+    mut toggle_events: EventReader<ToggleCrtEvent>,
+    mut settings: ResMut<CrtSettings>,
+) {
+    // Toggle once per event, so two toggles in a frame cancel out.
+    for _ in toggle_events.iter() {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+// An image the world camera can render into, at the display's resolution.
+fn render_image(display: &Display) -> Image {
+    let size = Extent3d {
+        width: display.width as u32,
+        height: display.height as u32,
+        ..Default::default()
+    };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..Default::default()
+    };
+    // Fill the image with zeroes, at its size.
+    image.resize(size);
+    image
+}
+
+// Set up or tear down the CRT screen when it's toggled, or when the display's resolution
+// changes and the rendered image needs a new size.
+#[allow(clippy::too_many_arguments)]
+fn apply_crt(
+    mut commands: Commands,
+    settings: Res<CrtSettings>,
+    display: Res<Display>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CrtMaterial>>,
+    mut cameras: Query<(Entity, &mut Camera, &mut OrthographicProjection), With<MainCamera>>,
+    screens: Query<Entity, With<CrtScreen>>,
+) {
+    if !settings.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let (camera_entity, mut camera, mut projection) = cameras.get_single_mut()
+        .expect("Camera not found.");
+    // Touch the projection, so it is fitted to the camera's new target.
+    projection.set_changed();
+
+    if !settings.enabled {
+        camera.target = RenderTarget::Window(WindowId::primary());
+        commands.entity(camera_entity).insert(UiCameraConfig { show_ui: true });
+        return;
+    }
+
+    let image = images.add(render_image(&display));
+    camera.target = RenderTarget::Image(image.clone());
+    // The UI is drawn over the screen instead, so the black bars stay at the window's edges.
+    commands.entity(camera_entity).insert(UiCameraConfig { show_ui: false });
+
+    commands
+        .spawn_bundle(Camera2dBundle {
+            camera: Camera {
+                // After the world camera, so its image is ready.
+                priority: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RenderLayers::layer(CRT_LAYER))
+        .insert(CrtScreen);
+
+    let size = Vec2::new(display.width, display.height);
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::new(size))).into(),
+            material: materials.add(CrtMaterial {
+                resolution: size,
+                settings: *settings,
+                image,
+            }),
+            ..Default::default()
+        })
+        .insert(RenderLayers::layer(CRT_LAYER))
+        .insert(CrtScreen);
+}
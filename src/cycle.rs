@@ -1,9 +1,15 @@
 use bevy::{
+    asset::HandleId,
     prelude::*,
     reflect::TypeUuid,
     render::render_resource::*,
     sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
 };
+use image::{Rgba, RgbaImage};
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+use super::clock::GameClock;
+use super::texture::from_texture;
 
 
 pub struct CyclePlugin;
@@ -12,8 +18,10 @@ impl Plugin for CyclePlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugin(Material2dPlugin::<CycleMaterial>::default())
+            .add_plugin(Material2dPlugin::<PaletteCycleMaterial>::default())
             // .add_startup_system(_spawn_test_mesh)
-            .add_system(update_material_elapsed_seconds);
+            .add_system(advance_material_time::<CycleMaterial>)
+            .add_system(advance_material_time::<PaletteCycleMaterial>);
     }
 }
 
@@ -26,6 +34,121 @@ pub struct CycleMaterial {
     #[texture(1)]
     #[sampler(2)]
     pub image: Option<Handle<Image>>,
+    // Nonzero to cycle the image's colours; zero to draw it unchanged.
+    #[uniform(3)]
+    pub enabled: u32,
+    #[uniform(4)]
+    pub settings: CycleSettings,
+    // How much of the effect to apply at each pixel, from the mask's red channel,
+    // so parts of an image can be left alone. Without a mask, the whole image cycles.
+    #[texture(5)]
+    #[sampler(6)]
+    pub mask: Option<Handle<Image>>,
+    pub time: CycleTime,
+}
+
+// How the colours cycle.
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct CycleSettings {
+    // Trips around the colour wheel per second. Negative values cycle the other way.
+    pub speed: f32,
+    // Trips around the colour wheel per pixel along the direction of the bands.
+    pub frequency: f32,
+    // Direction the bands of colour run in, in radians, in image space with y pointing down.
+    pub angle: f32,
+    // Multipliers for the saturation and value of the cycled colours.
+    pub saturation: f32,
+    pub value: f32,
+}
+impl Default for CycleSettings {
+    fn default() -> Self {
+        // Diagonal bands, a full cycle every hundred pixels along each axis.
+        CycleSettings {
+            speed: 1.,
+            frequency: SQRT_2 / 100.,
+            angle: FRAC_PI_4,
+            saturation: 1.,
+            value: 1.,
+        }
+    }
+}
+impl CycleSettings {
+    // A slow, narrow shift of hue, for highlighting without drawing much attention.
+    pub fn shimmer() -> Self {
+        CycleSettings {
+            speed: 0.25,
+            frequency: 0.002,
+            saturation: 0.5,
+            ..Default::default()
+        }
+    }
+
+    // Fast, fully saturated horizontal stripes of every colour.
+    pub fn rainbow() -> Self {
+        CycleSettings {
+            speed: 2.,
+            frequency: 0.05,
+            angle: 0.,
+            saturation: 1.5,
+            ..Default::default()
+        }
+    }
+}
+
+// How a material's animation runs against the game clock.
+// Materials start from zero when they are created, so give them an offset to keep them in step.
+#[derive(Clone, Copy, Debug)]
+pub struct CycleTime {
+    // Seconds added to the material's time, to start it part way through its cycle.
+    pub offset: f32,
+    // How fast the material's time runs, relative to its clock.
+    pub speed: f32,
+    pub paused: bool,
+    // Run on real time instead of the game clock, so the material keeps animating
+    // while the game is paused, as menus should.
+    pub real_time: bool,
+    // Seconds the material has animated for, scaled by its speed.
+    pub elapsed: f64,
+}
+impl Default for CycleTime {
+    fn default() -> Self {
+        CycleTime {
+            offset: 0.,
+            speed: 1.,
+            paused: false,
+            real_time: false,
+            elapsed: 0.,
+        }
+    }
+}
+impl CycleTime {
+    fn seconds(&self) -> f32 {
+        (self.elapsed + self.offset as f64) as f32
+    }
+}
+
+// A material whose elapsed_seconds uniform is driven by a CycleTime.
+pub trait CycleTimed: Material2d {
+    fn time(&self) -> &CycleTime;
+    fn time_mut(&mut self) -> &mut CycleTime;
+    fn elapsed_seconds(&self) -> f32;
+    fn set_elapsed_seconds(&mut self, seconds: f32);
+
+    // Whether the material's time moves at all; others are left alone.
+    fn is_animating(&self) -> bool {
+        !self.time().paused && self.time().speed != 0.
+    }
+}
+
+impl CycleTimed for CycleMaterial {
+    fn time(&self) -> &CycleTime { &self.time }
+    fn time_mut(&mut self) -> &mut CycleTime { &mut self.time }
+    fn elapsed_seconds(&self) -> f32 { self.elapsed_seconds }
+    fn set_elapsed_seconds(&mut self, seconds: f32) { self.elapsed_seconds = seconds; }
+
+    fn is_animating(&self) -> bool {
+        self.enabled != 0 && !self.time.paused && self.time.speed != 0.
+    }
 }
 
 impl Material2d for CycleMaterial {
@@ -34,12 +157,138 @@ impl Material2d for CycleMaterial {
     }
 }
 
-fn update_material_elapsed_seconds(
-    mut materials: ResMut<Assets<CycleMaterial>>,
-    time: Res<Time>,
+pub const MAX_PALETTE_RANGES: usize = 8;
+
+// Palette cycling, as in Deluxe Paint: each pixel of the image is an index into a palette,
+// and ranges of the palette rotate over time, so the pixels using them animate.
+#[derive(AsBindGroup, Clone, Debug, Default, TypeUuid)]
+#[uuid = "3b0b8d8c-5f0e-4d5a-9b7e-2f6c1d9a4e21"]
+pub struct PaletteCycleMaterial {
+    #[uniform(0)]
+    pub elapsed_seconds: f32,
+    // Each pixel's palette index in its red channel, as made by palette_indices.
+    #[texture(1)]
+    pub indices: Option<Handle<Image>>,
+    // The colours, read left to right and top to bottom.
+    #[texture(2)]
+    pub palette: Option<Handle<Image>>,
+    // Pixels use the first range their index is in. Unused ranges are left empty.
+    #[uniform(3)]
+    pub ranges: [PaletteRange; MAX_PALETTE_RANGES],
+    pub time: CycleTime,
+}
+
+impl CycleTimed for PaletteCycleMaterial {
+    fn time(&self) -> &CycleTime { &self.time }
+    fn time_mut(&mut self) -> &mut CycleTime { &mut self.time }
+    fn elapsed_seconds(&self) -> f32 { self.elapsed_seconds }
+    fn set_elapsed_seconds(&mut self, seconds: f32) { self.elapsed_seconds = seconds; }
+}
+
+impl Material2d for PaletteCycleMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "palette_cycle.wgsl".into()
+    }
+}
+
+// A range of palette indices, from start to end inclusive, that rotates as a loop.
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct PaletteRange {
+    pub start: u32,
+    pub end: u32,
+    // Steps per second. Positive speeds move each colour towards the end of the range.
+    pub speed: f32,
+    // Steps to rotate by at time zero, to stagger ranges that share a speed.
+    pub offset: f32,
+}
+impl PaletteRange {
+    pub fn new(start: u32, end: u32, speed: f32) -> Self {
+        PaletteRange {
+            start,
+            end,
+            speed,
+            offset: 0.,
+        }
+    }
+}
+
+// Turn an image drawn with a palette's colours into an image of indices into the palette,
+// for a PaletteCycleMaterial. Colours that aren't in the palette use index 0.
+// Returns None if either image isn't RGBA.
+pub fn palette_indices(image: &Image, palette: &Image) -> Option<Image> {
+    let indices = index_image(&from_texture(image)?, &from_texture(palette)?);
+
+    // Linear rather than sRGB, so the shader reads back the exact indices.
+    Some(Image::new(
+        Extent3d {
+            width: indices.width(),
+            height: indices.height(),
+            ..Default::default()
+        },
+        TextureDimension::D2,
+        indices.into_raw(),
+        TextureFormat::Rgba8Unorm,
+    ))
+}
+
+fn index_image(image: &RgbaImage, palette: &RgbaImage) -> RgbaImage {
+    let colors: Vec<&Rgba<u8>> = palette.pixels().collect();
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        if pixel[3] == 0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let index = colors.iter().position(|&color| color == pixel).unwrap_or(0);
+        Rgba([index.min(255) as u8, 0, 0, 255])
+    })
+}
+
+// Advance the time of each animating material.
+// Getting a material mutably re-uploads it, so the rest aren't touched.
+pub fn advance_material_time<M: CycleTimed>(
+    mut materials: ResMut<Assets<M>>,
+    mut material_events: EventReader<AssetEvent<M>>,
+    clock: Res<GameClock>,
+    real_time: Res<Time>,
 ) {
-    for (_, material) in materials.iter_mut() {
-        material.elapsed_seconds = time.seconds_since_startup() as f32;
+    // Start new and changed materials at their offset, even ones that don't animate,
+    // so a paused material is still drawn part way through its cycle.
+    let changed: Vec<HandleId> = material_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    for id in changed {
+        // Only touch materials that are out of date, as getting one mutably modifies it again.
+        let handle = Handle::weak(id);
+        if materials.get(&handle).is_some_and(|m| m.elapsed_seconds() != m.time().seconds()) {
+            if let Some(material) = materials.get_mut(&handle) {
+                let seconds = material.time().seconds();
+                material.set_elapsed_seconds(seconds);
+            }
+        }
+    }
+
+    let game_delta = clock.delta().as_secs_f64();
+    let real_delta = real_time.delta_seconds_f64();
+
+    // Materials on the game clock stand still while the game is paused, so skip them then.
+    let animating: Vec<HandleId> = materials.iter()
+        .filter(|(_, material)| material.is_animating())
+        .filter(|(_, material)| material.time().real_time || game_delta > 0.)
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in animating {
+        if let Some(material) = materials.get_mut(&Handle::weak(id)) {
+            let time = material.time_mut();
+            let delta = if time.real_time { real_delta } else { game_delta };
+            time.elapsed += delta * time.speed as f64;
+            let seconds = time.seconds();
+            material.set_elapsed_seconds(seconds);
+        }
     }
 }
 
@@ -61,6 +310,7 @@ fn _spawn_test_mesh(
         },
         material: materials.add(CycleMaterial {
             image: Some(image),
+            enabled: 1,
             ..Default::default()
         }),
         ..Default::default()
@@ -55,14 +55,24 @@ enum Edge {
 #[derive(Component)]
 struct BlackBar(Edge);
 
+// Marks an entity as part of the screen rather than the world,
+// so it keeps its place on screen when the camera moves to a new display.
+#[derive(Component)]
+pub struct FollowCamera;
+
+pub struct SwitchResolutionEvent;
+
 pub struct DisplayPlugin;
 
 impl Plugin for DisplayPlugin {
     fn build (&self, app: &mut App) {
         app
+            .add_event::<SwitchResolutionEvent>()
             .add_startup_system(init_display)
             .add_system(switch_resolution_on_space)
-            .add_system(handle_resize);
+            .add_system(switch_resolution.after(switch_resolution_on_space))
+            .add_system(handle_resize)
+            .add_system(follow_camera);
     }
 }
 
@@ -78,15 +88,24 @@ fn init_display(
 }
 
 fn switch_resolution_on_space(
+    keys: Res<Input<KeyCode>>,
+    mut resolution_events: EventWriter<SwitchResolutionEvent>,
+) {
+    if keys.just_released(KeyCode::Space) {
+        resolution_events.send(SwitchResolutionEvent);
+    }
+}
+
+fn switch_resolution(
     mut commands: Commands,
+    mut resolution_events: EventReader<SwitchResolutionEvent>,
     mut cursor_events: EventWriter<CursorMoved>,
-    keys: Res<Input<KeyCode>>,
     mut windows: ResMut<Windows>,
     mut cameras: Query<&mut Transform, With<MainCamera>>,
     display: Res<Display>,
     mut black_bars: Query<(&BlackBar, &mut Style)>,
 ) {
-    if keys.just_released(KeyCode::Space) {
+    if resolution_events.iter().last().is_some() {
         let window = windows.get_primary_mut().expect("Window not found.");
         let mut camera = cameras.get_single_mut().expect("Camera not found.");
         let index = match DISPLAYS.iter()
@@ -121,6 +140,23 @@ fn handle_resize(
     }
 }
 
+fn follow_camera(
+    mut last_position: Local<Option<Vec3>>,
+    cameras: Query<&Transform, (With<MainCamera>, Changed<Transform>)>,
+    mut followers: Query<&mut Transform, (With<FollowCamera>, Without<MainCamera>)>,
+) {
+    if let Ok(camera_transform) = cameras.get_single() {
+        let position = camera_transform.translation;
+        if let Some(last) = *last_position {
+            let delta = Vec3::new(position.x - last.x, position.y - last.y, 0.);
+            for mut transform in followers.iter_mut() {
+                transform.translation += delta;
+            }
+        }
+        *last_position = Some(position);
+    }
+}
+
 fn size_window(
     window: &mut Window,
     camera_transform: &mut Transform,
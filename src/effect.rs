@@ -0,0 +1,377 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{render_asset::RenderAssets, render_resource::*},
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use super::clock::GameClock;
+use super::cycle::{advance_material_time, CycleTime, CycleTimed};
+
+
+// Sprite effects, each a Material2d drawing an image with a shader.
+// Draw them on a quad with effect_bundle, in place of a SpriteBundle.
+pub struct EffectPlugin;
+
+impl Plugin for EffectPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugin(Material2dPlugin::<OutlineMaterial>::default())
+            .add_plugin(Material2dPlugin::<FlashMaterial>::default())
+            .add_plugin(Material2dPlugin::<DissolveMaterial>::default())
+            .add_plugin(Material2dPlugin::<PaletteSwapMaterial>::default())
+            .add_plugin(Material2dPlugin::<ScanlineMaterial>::default())
+            .add_plugin(Material2dPlugin::<WobbleMaterial>::default())
+            .add_startup_system(create_effect_mesh)
+            .add_system(fade_flashes)
+            .add_system(advance_material_time::<WobbleMaterial>);
+    }
+}
+
+// A unit quad, scaled to the size of each image drawn with an effect.
+pub struct EffectMesh(pub Mesh2dHandle);
+
+fn create_effect_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.insert_resource(EffectMesh(meshes.add(Mesh::from(shape::Quad::default())).into()));
+}
+
+// Draw an effect material at a size, the way a SpriteBundle would draw its image.
+pub fn effect_bundle<M: Material2d>(
+    mesh: &EffectMesh,
+    material: Handle<M>,
+    size: Vec2,
+    translation: Vec3,
+) -> MaterialMesh2dBundle<M> {
+    MaterialMesh2dBundle {
+        mesh: mesh.0.clone(),
+        material,
+        transform: Transform {
+            scale: size.extend(1.),
+            translation,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// Draw a line around the opaque parts of an image.
+// The outline is drawn inside the image's bounds, so it needs a transparent margin.
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "0f5e7a3c-93b1-4b8e-a5a4-6d1c2e8f7b10"]
+#[uniform(0, OutlineUniform)]
+pub struct OutlineMaterial {
+    pub color: Color,
+    // In image pixels.
+    pub thickness: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+impl Default for OutlineMaterial {
+    fn default() -> Self {
+        OutlineMaterial {
+            color: Color::WHITE,
+            thickness: 1.,
+            image: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct OutlineUniform {
+    color: Vec4,
+    thickness: f32,
+}
+impl AsBindGroupShaderType<OutlineUniform> for OutlineMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> OutlineUniform {
+        OutlineUniform {
+            color: self.color.as_linear_rgba_f32().into(),
+            thickness: self.thickness,
+        }
+    }
+}
+
+impl Material2d for OutlineMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/outline.wgsl".into()
+    }
+}
+
+// Mix an image towards a colour, as when something is hit.
+// Add a Flash to the entity to fade the mix out over time.
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "5c2d8e41-7a6f-4f0b-8c3e-1b9d4a6e2f37"]
+#[uniform(0, FlashUniform)]
+pub struct FlashMaterial {
+    pub color: Color,
+    // From 0, the image as it is, to 1, the image's shape filled with the colour.
+    pub amount: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+impl Default for FlashMaterial {
+    fn default() -> Self {
+        FlashMaterial {
+            color: Color::WHITE,
+            amount: 0.,
+            image: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct FlashUniform {
+    color: Vec4,
+    amount: f32,
+}
+impl AsBindGroupShaderType<FlashUniform> for FlashMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> FlashUniform {
+        FlashUniform {
+            color: self.color.as_linear_rgba_f32().into(),
+            amount: self.amount,
+        }
+    }
+}
+
+impl Material2d for FlashMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/flash.wgsl".into()
+    }
+}
+
+// Flash an entity's FlashMaterial fully, then fade it out. Removed once it has faded.
+#[derive(Component)]
+pub struct Flash {
+    pub duration: f32,
+    pub remaining: f32,
+}
+impl Flash {
+    pub fn new(duration: f32) -> Self {
+        Flash {
+            duration,
+            remaining: duration,
+        }
+    }
+}
+
+fn fade_flashes(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<FlashMaterial>>,
+    mut flashes: Query<(Entity, &mut Flash, &Handle<FlashMaterial>)>,
+    clock: Res<GameClock>,
+) {
+    for (entity, mut flash, handle) in flashes.iter_mut() {
+        flash.remaining = (flash.remaining - clock.delta().as_secs_f32()).max(0.);
+
+        if let Some(material) = materials.get_mut(handle) {
+            material.amount = if flash.duration > 0. {
+                flash.remaining / flash.duration
+            } else {
+                0.
+            };
+        }
+
+        if flash.remaining <= 0. {
+            commands.entity(entity).remove::<Flash>();
+        }
+    }
+}
+
+// Eat away at an image in a scattered pattern, with a glowing edge along the way.
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "b8a1f6d2-2c47-4e93-9f15-7e3a0c5d8b64"]
+#[uniform(0, DissolveUniform)]
+pub struct DissolveMaterial {
+    // From 0, the whole image, to 1, nothing left.
+    pub amount: f32,
+    pub edge_color: Color,
+    // How much further than the dissolved part the edge reaches, from 0 to 1.
+    pub edge_width: f32,
+    // Size of the dissolving blocks, in image pixels.
+    pub block_size: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+impl Default for DissolveMaterial {
+    fn default() -> Self {
+        DissolveMaterial {
+            amount: 0.,
+            edge_color: Color::ORANGE,
+            edge_width: 0.1,
+            block_size: 1.,
+            image: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct DissolveUniform {
+    edge_color: Vec4,
+    amount: f32,
+    edge_width: f32,
+    block_size: f32,
+}
+impl AsBindGroupShaderType<DissolveUniform> for DissolveMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> DissolveUniform {
+        DissolveUniform {
+            edge_color: self.edge_color.as_linear_rgba_f32().into(),
+            amount: self.amount,
+            edge_width: self.edge_width,
+            block_size: self.block_size.max(1.),
+        }
+    }
+}
+
+impl Material2d for DissolveMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/dissolve.wgsl".into()
+    }
+}
+
+pub const MAX_PALETTE_SWAPS: usize = 8;
+
+// Replace colours in an image with others, such as to draw a topping in another flavour.
+#[derive(AsBindGroup, Clone, Debug, Default, TypeUuid)]
+#[uuid = "e4c7b2a9-6d13-48f5-a0e8-3f9b1c7d5a82"]
+#[uniform(0, PaletteSwapUniform)]
+pub struct PaletteSwapMaterial {
+    // Each colour to replace, and what to replace it with.
+    // Only the first MAX_PALETTE_SWAPS are used.
+    pub swaps: Vec<(Color, Color)>,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct PaletteSwapUniform {
+    from: [Vec4; MAX_PALETTE_SWAPS],
+    to: [Vec4; MAX_PALETTE_SWAPS],
+    count: u32,
+}
+impl AsBindGroupShaderType<PaletteSwapUniform> for PaletteSwapMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> PaletteSwapUniform {
+        let mut uniform = PaletteSwapUniform::default();
+        for (i, (from, to)) in self.swaps.iter().take(MAX_PALETTE_SWAPS).enumerate() {
+            uniform.from[i] = from.as_linear_rgba_f32().into();
+            uniform.to[i] = to.as_linear_rgba_f32().into();
+            uniform.count += 1;
+        }
+        uniform
+    }
+}
+
+impl Material2d for PaletteSwapMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/palette_swap.wgsl".into()
+    }
+}
+
+// Darken every few rows of an image, like the scanlines of an old screen.
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "71d3e9b5-4f28-4c6a-b1d7-8e2a5f0c9b43"]
+#[uniform(0, ScanlineUniform)]
+pub struct ScanlineMaterial {
+    // Rows from the start of one scanline to the next, in image pixels.
+    pub spacing: f32,
+    // How much the scanlines darken the image, from 0 to 1.
+    pub darkness: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+impl Default for ScanlineMaterial {
+    fn default() -> Self {
+        ScanlineMaterial {
+            spacing: 2.,
+            darkness: 0.3,
+            image: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct ScanlineUniform {
+    spacing: f32,
+    darkness: f32,
+}
+impl AsBindGroupShaderType<ScanlineUniform> for ScanlineMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> ScanlineUniform {
+        ScanlineUniform {
+            spacing: self.spacing.max(1.),
+            darkness: self.darkness,
+        }
+    }
+}
+
+impl Material2d for ScanlineMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/scanline.wgsl".into()
+    }
+}
+
+// Shift each row of an image from side to side in a wave, like a reflection in water.
+#[derive(AsBindGroup, Clone, Debug, TypeUuid)]
+#[uuid = "9a6f0c18-d3e2-47b9-85a1-c4e7b2d6f059"]
+#[uniform(0, WobbleUniform)]
+pub struct WobbleMaterial {
+    // Furthest a row moves, in image pixels.
+    pub amplitude: f32,
+    // Waves from the top of the image to the bottom.
+    pub frequency: f32,
+    // Waves passing each row per second.
+    pub speed: f32,
+    pub elapsed_seconds: f32,
+    pub time: CycleTime,
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Option<Handle<Image>>,
+}
+impl Default for WobbleMaterial {
+    fn default() -> Self {
+        WobbleMaterial {
+            amplitude: 1.,
+            frequency: 2.,
+            speed: 1.,
+            elapsed_seconds: 0.,
+            time: CycleTime::default(),
+            image: None,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct WobbleUniform {
+    amplitude: f32,
+    frequency: f32,
+    speed: f32,
+    elapsed_seconds: f32,
+}
+impl AsBindGroupShaderType<WobbleUniform> for WobbleMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> WobbleUniform {
+        WobbleUniform {
+            amplitude: self.amplitude,
+            frequency: self.frequency,
+            speed: self.speed,
+            elapsed_seconds: self.elapsed_seconds,
+        }
+    }
+}
+
+impl CycleTimed for WobbleMaterial {
+    fn time(&self) -> &CycleTime { &self.time }
+    fn time_mut(&mut self) -> &mut CycleTime { &mut self.time }
+    fn elapsed_seconds(&self) -> f32 { self.elapsed_seconds }
+    fn set_elapsed_seconds(&mut self, seconds: f32) { self.elapsed_seconds = seconds; }
+}
+
+impl Material2d for WobbleMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "effects/wobble.wgsl".into()
+    }
+}
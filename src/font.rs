@@ -1,16 +1,31 @@
 use bevy::{
+    input::{
+        ButtonState,
+        mouse::{MouseButton, MouseButtonInput},
+    },
+    math::Vec3Swizzles,
     prelude::*,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
-    sprite::MaterialMesh2dBundle,
+    sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle, Rect},
+    utils::HashMap,
 };
 use bevy_asset_loader::prelude::*;
 use image::{
     GenericImageView, Rgba, RgbaImage,
-    imageops::overlay,
+    imageops::{overlay, resize, FilterType},
 };
+use std::{fs, path::Path};
 
-use super::bounce::{Bounce, BounceEffect, EffectType};
-use super::cycle::CycleMaterial;
+use super::MainCamera;
+use super::cursor;
+use super::cycle::{CycleMaterial, CycleSettings, CycleTime};
+use super::font_descriptor::{
+    AtlasLayout, FontAsset, FontDescriptor, FontDescriptorLoader, GlyphMetrics, GlyphWidths,
+};
+use super::markup::{self, RichText};
+use super::panel::{draw_nine_slice, NineSlice, PanelAssets};
+use super::state::GameState;
+use super::texture::{from_texture, to_texture};
 
 
 pub struct FontPlugin;
@@ -18,131 +33,275 @@ pub struct FontPlugin;
 impl Plugin for FontPlugin {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(TextIcons::default())
+            .add_asset::<FontAsset>()
+            .init_asset_loader::<FontDescriptorLoader>()
             .add_loading_state(
-                LoadingState::new(FontState::FontsLoading)
-                    .continue_to_state(FontState::FontsReady)
-                    .with_collection::<FontAssets>())
-            .add_state(FontState::FontsLoading)
-            .add_system_set(SystemSet::on_exit(FontState::FontsLoading)
+                LoadingState::new(GameState::Loading)
+                    .continue_to_state(GameState::Title)
+                    .with_collection::<FontAssets>()
+                    .with_collection::<PanelAssets>())
+            .add_system_set(SystemSet::on_exit(GameState::Loading)
                 .with_system(load_fonts))
-            .add_system_set(SystemSet::on_enter(FontState::FontsReady)
-                .with_system(create_text))
-            .add_system(render_text);
+            .add_startup_system(create_text_mesh)
+            .add_system(render_text)
+            .add_system(turn_pages);
     }
 }
 
-// Bevy Asset Loader
-
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
-enum FontState {
-    FontsLoading,
-    FontsReady,
+// A font's descriptor, the image its glyphs are cut from, where each glyph is in that image,
+// and how each glyph is measured. This is all that's needed to render text to an image,
+// so it can be used without Bevy. The texture atlas is only used to render glyphs as sprites.
+#[derive(Component)]
+pub struct BitmapFont {
+    info: FontDescriptor,
+    atlas_image: RgbaImage,
+    // Each glyph's rect in the atlas image, in atlas order.
+    rects: Vec<Rect>,
+    // Metrics for each glyph, in atlas order.
+    metrics: Vec<GlyphMetrics>,
+    texture_atlas_handle: Handle<TextureAtlas>,
 }
+impl BitmapFont {
+    pub fn new(info: FontDescriptor, atlas_image: RgbaImage) -> Self {
+        let rects = match &info.atlas {
+            // Row by row, as TextureAtlas::from_grid lays them out.
+            AtlasLayout::Grid(grid_size) => (0..grid_size.y as usize)
+                .flat_map(|y| (0..grid_size.x as usize).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let min = Vec2::new(x as f32, y as f32) * info.tile_size;
+                    Rect { min, max: min + info.tile_size }
+                })
+                .collect(),
+            AtlasLayout::Rects(rects) => rects.clone(),
+        };
+
+        let metrics = match &info.glyph_widths {
+            GlyphWidths::Fixed => vec![],
+            GlyphWidths::Auto => measure_glyphs(&rects, &atlas_image),
+            GlyphWidths::Manual(widths) => widths.iter()
+                .map(|&width| GlyphMetrics::columns(0., width))
+                .collect(),
+            GlyphWidths::Metrics(metrics) => metrics.clone(),
+        };
+
+        BitmapFont {
+            info,
+            atlas_image,
+            rects,
+            metrics,
+            texture_atlas_handle: Handle::default(),
+        }
+    }
+
+    // Load a font from a descriptor file and the image it names, for use outside Bevy.
+    pub fn from_file(descriptor_path: &Path) -> Result<Self, anyhow::Error> {
+        let descriptor = match descriptor_path.extension().and_then(|e| e.to_str()) {
+            Some("fnt") => FontDescriptor::from_bmfont(&fs::read_to_string(descriptor_path)?)?,
+            _ => FontDescriptor::from_json(&fs::read(descriptor_path)?)?,
+        };
+
+        let directory = descriptor_path.parent().unwrap_or_else(|| Path::new(""));
+        let atlas_image = image::open(directory.join(&descriptor.image))?.to_rgba8();
+        descriptor.check_atlas_size(
+            Vec2::new(atlas_image.width() as f32, atlas_image.height() as f32))?;
+
+        Ok(BitmapFont::new(descriptor, atlas_image))
+    }
+
+    pub fn info(&self) -> &FontDescriptor {
+        &self.info
+    }
 
-enum CharMap {
-    Ascii,
-    TSK,
+    // A texture atlas with the font's glyph rects, for rendering glyphs as sprites.
+    fn texture_atlas(&self, texture: Handle<Image>) -> TextureAtlas {
+        let size = Vec2::new(self.atlas_image.width() as f32, self.atlas_image.height() as f32);
+        let mut texture_atlas = TextureAtlas::new_empty(texture, size);
+        for rect in self.rects.iter() {
+            texture_atlas.add_texture(*rect);
+        }
+        texture_atlas
+    }
+
+    fn glyph_metrics(&self, index: usize) -> GlyphMetrics {
+        self.metrics.get(index).copied()
+            .unwrap_or(GlyphMetrics::columns(0., self.info.tile_size.x))
+    }
 }
 
-struct FontInfo {
-    name: String,
-    asset_handle: Handle<Image>,
-    tile_size: Vec2,
-    grid_size: Vec2,
-    char_map: CharMap,
+// A text's font, followed by the fonts it falls back to for characters it doesn't have.
+struct FontChain<'a> {
+    fonts: Vec<&'a BitmapFont>,
 }
+impl<'a> FontChain<'a> {
+    fn new(name: &str, find: &impl Fn(&str) -> Option<&'a BitmapFont>) -> Self {
+        let font = find(name).expect("Font not found.");
 
-#[derive(Component)]
-struct BitmapFont {
-    info: FontInfo,
-    texture_atlas_handle: Handle<TextureAtlas>,
+        // Fallbacks that aren't available are skipped, so a font can be rendered on its own.
+        let mut chain = vec![font];
+        chain.extend(font.info.fallback_fonts.iter().filter_map(|fallback| find(fallback)));
+
+        FontChain {
+            fonts: chain,
+        }
+    }
+
+    fn primary(&self) -> &'a BitmapFont {
+        self.fonts[0]
+    }
+
+    // The first font in the chain with a glyph for the character, and the glyph's atlas index.
+    // Whitespace only comes from the primary font, so spacing isn't borrowed from another font.
+    // A character only counts if its index has a rect in the atlas.
+    fn find_glyph(&self, c: char) -> Option<(&'a BitmapFont, usize)> {
+        let fonts = if c.is_whitespace() { &self.fonts[..1] } else { &self.fonts[..] };
+        fonts.iter().find_map(|font| font.info.char_map.index(c)
+            .filter(|&index| font.rects.get(index).is_some())
+            .map(|index| (*font, index)))
+    }
+
+    // The glyph to draw for a character, using the fallback character if no font has it,
+    // and its metrics. Characters with no glyph at all still take up a whole tile,
+    // and icons take up a square the height of a tile. Whitespace is never drawn as the
+    // fallback character; without a glyph it is left blank, half a tile wide like empty tiles.
+    fn resolve(&self, c: char) -> (Option<(&'a BitmapFont, usize)>, GlyphMetrics) {
+        let primary = self.primary();
+        if markup::is_icon_char(c) {
+            return (None, GlyphMetrics::columns(0., primary.info.tile_size.y));
+        }
+
+        let glyph = self.find_glyph(c);
+        if glyph.is_none() && c.is_whitespace() {
+            return (None, GlyphMetrics::columns(0., (primary.info.tile_size.x / 2.).round()));
+        }
+        let glyph = glyph
+            .or_else(|| primary.info.fallback_char.and_then(|f| self.find_glyph(f)));
+
+        let metrics = match glyph {
+            Some((font, index)) => {
+                let mut metrics = font.glyph_metrics(index);
+                // Line up the bottoms of glyphs taken from shorter or taller fonts.
+                metrics.offset.y += primary.info.tile_size.y - font.info.tile_size.y;
+                metrics
+            }
+            None => GlyphMetrics::columns(0., primary.info.tile_size.x),
+        };
+
+        (glyph, metrics)
+    }
+
+    // The width of a line of text, with letter spacing between each character.
+    fn measure(&self, text: &str) -> f32 {
+        let advances: Vec<f32> = text.chars().map(|c| self.resolve(c).1.advance).collect();
+        advances.iter().sum::<f32>()
+            + advances.len().saturating_sub(1) as f32 * self.primary().info.letter_spacing
+    }
+
+    // Characters in the text that no font in the chain has, each listed once.
+    fn missing_chars(&self, text: &str) -> Vec<char> {
+        let mut missing = vec![];
+        for c in text.chars() {
+            if !c.is_whitespace() && !markup::is_icon_char(c) && self.find_glyph(c).is_none()
+                && !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+        missing
+    }
 }
 
+// Bevy Asset Loader
+
 #[derive(AssetCollection)]
 struct FontAssets {
-    #[asset(path = "fonts/32X32-FA.png")]
-    bluepink: Handle<Image>,
-    #[asset(path = "fonts/dinobyte.png")]
-    dinobyte: Handle<Image>,
-    #[asset(path = "fonts/elecfont.png")]
-    elecfont: Handle<Image>,
-    #[asset(path = "fonts/fantfont.png")]
-    fantfont: Handle<Image>,
-    #[asset(path = "fonts/geebeeyay-8x8.png")]
-    geebee: Handle<Image>,
-    #[asset(path = "fonts/TSK03.png")]
-    tsk: Handle<Image>,
+    // Everything in the fonts folder, so that adding a font only needs its image and descriptor.
+    #[asset(path = "fonts", collection)]
+    files: Vec<HandleUntyped>,
 }
 
 fn load_fonts(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
+    fonts: Res<Assets<FontAsset>>,
+    images: Res<Assets<Image>>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    let fonts = vec![
-        FontInfo {
-            name: "BluePink".into(),
-            asset_handle: font_assets.bluepink.clone(),
-            tile_size: Vec2::new(32., 32.),
-            grid_size: Vec2::new(10., 6.),
-            char_map: CharMap::Ascii,
-        },
-        FontInfo {
-            name: "Dinobyte".into(),
-            asset_handle: font_assets.dinobyte.clone(),
-            tile_size: Vec2::new(6., 8.),
-            grid_size: Vec2::new(16., 6.),
-            char_map: CharMap::Ascii,
-        },
-        FontInfo {
-            name: "Elecfont".into(),
-            asset_handle: font_assets.elecfont.clone(),
-            tile_size: Vec2::new(32., 32.),
-            grid_size: Vec2::new(10., 6.),
-            char_map: CharMap::Ascii,
-        },
-        FontInfo {
-            name: "Fantfont".into(),
-            asset_handle: font_assets.fantfont.clone(),
-            tile_size: Vec2::new(32., 32.),
-            grid_size: Vec2::new(10., 6.),
-            char_map: CharMap::Ascii,
-        },
-        FontInfo {
-            name: "GeeBee".into(),
-            asset_handle: font_assets.geebee.clone(),
-            tile_size: Vec2::new(8., 8.),
-            grid_size: Vec2::new(77., 1.),
-            char_map: CharMap::Ascii,
-        },
-        FontInfo {
-            name: "TSK".into(),
-            asset_handle: font_assets.tsk.clone(),
-            tile_size: Vec2::new(32., 20.),
-            grid_size: Vec2::new(10., 4.),
-            char_map: CharMap::TSK,
-        },
-    ];
+    for handle in font_assets.files.iter() {
+        // Skip the images, which are loaded by their descriptors.
+        let font = match fonts.get(&handle.clone().typed::<FontAsset>()) {
+            Some(font) => font,
+            None => continue,
+        };
 
-    for font in fonts {
-        let texture_atlas = TextureAtlas::from_grid(font.asset_handle.clone(), font.tile_size,
-            font.grid_size.x as usize, font.grid_size.y as usize);
+        let texture = images.get(&font.image).expect("Font image not found.");
+        let atlas_image = from_texture(texture).expect("Atlas image not found.");
+        // The descriptor can't see its image when it is loaded, so check they match here.
+        let size = Vec2::new(atlas_image.width() as f32, atlas_image.height() as f32);
+        if let Err(error) = font.descriptor.check_atlas_size(size) {
+            error!("{}", error);
+            continue;
+        }
 
-        commands.spawn().insert(BitmapFont {
-            info: font,
-            texture_atlas_handle: texture_atlases.add(texture_atlas),
-        });
+        let mut bitmap_font = BitmapFont::new(font.descriptor.clone(), atlas_image);
+        bitmap_font.texture_atlas_handle =
+            texture_atlases.add(bitmap_font.texture_atlas(font.image.clone()));
+        commands.spawn().insert(bitmap_font);
     }
 }
 
+// Find each glyph's extent from the opaque columns of its tile.
+// Empty tiles, such as the space, get half a tile's width.
+fn measure_glyphs(rects: &[Rect], atlas_image: &RgbaImage) -> Vec<GlyphMetrics> {
+    rects.iter()
+        .map(|rect| {
+            let opaque: Vec<u32> = (0..rect.width() as u32)
+                .filter(|&x| (0..rect.height() as u32).any(|y| {
+                    atlas_image.get_pixel(rect.min.x as u32 + x, rect.min.y as u32 + y)[3] > 0
+                }))
+                .collect();
+
+            match (opaque.first(), opaque.last()) {
+                (Some(&left), Some(&right)) =>
+                    GlyphMetrics::columns(left as f32, (right - left + 1) as f32),
+                _ => GlyphMetrics::columns(0., (rect.width() / 2.).round()),
+            }
+        })
+        .collect()
+}
+
 #[derive(Component)]
-struct BitmapText {
-    text: String,
-    font: String,
-    position: Vec3,
-    box_size: Vec2,
-    padding: f32,
-    background_color: Color,
+pub struct BitmapText {
+    pub text: String,
+    pub font: String,
+    // Where the anchor point of the text box is placed.
+    pub position: Vec3,
+    pub anchor: Anchor,
+    // The size of the text box. A zero width or height fits the box to the text on that axis:
+    // lines only wrap when there is a width, and only overflow when there is a height.
+    pub box_size: Vec2,
+    pub padding: f32,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    // Add a hyphen where a word too long for a line is broken.
+    pub hyphenate: bool,
+    // What to do with lines that don't fit in the box.
+    pub overflow: TextOverflow,
+    // Colours multiplied into the glyphs' own colours.
+    pub fill: TextFill,
+    // A one pixel line around the glyphs. Only applies to image rendering.
+    pub outline: Option<Color>,
+    // Only applies to image rendering.
+    pub shadow: Option<TextShadow>,
+    // The page shown when overflow is Paginate.
+    pub page: usize,
+    pub background_color: Color,
+    // A panel drawn over the background colour, filling the box.
+    pub background: Option<NineSlice>,
+    // Cycle the text's colours with the CycleMaterial effect. Only applies to image rendering.
+    pub cycle: bool,
+    pub cycle_settings: CycleSettings,
+    pub cycle_time: CycleTime,
+    pub render_mode: TextRenderMode,
 }
 impl Default for BitmapText {
     fn default() -> Self {
@@ -150,192 +309,1070 @@ impl Default for BitmapText {
             text: "".into(),
             font: "".into(),
             position: Vec3::ZERO,
+            anchor: Anchor::Center,
             box_size: Vec2::ZERO,
             padding: 0.,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            hyphenate: false,
+            overflow: TextOverflow::Clip,
+            page: 0,
+            fill: TextFill::Solid(Color::WHITE),
+            outline: None,
+            shadow: None,
             background_color: Color::NONE,
+            background: None,
+            cycle: false,
+            cycle_settings: CycleSettings::default(),
+            cycle_time: CycleTime::default(),
+            render_mode: TextRenderMode::Image,
         }
     }
 }
 
-fn create_text(
-    mut commands: Commands
-) {
-    // commands.spawn().insert(BitmapText {
-    //     text: "Nemo et voluptas et cumque ipsum cumque inventore. Eveniet soluta odio sint aut asperiores et. Maxime unde cupiditate sunt dolor corporis nihil.".to_uppercase(),
-    //     font: "GeeBee".into(),
-    //     position: Vec3::new(-100., 0., 2.),
-    //     box_size: Vec2::new(160., 200.),
-    //     padding: 4.,
-    //     background_color: Color::rgb(0.1, 0.1, 0.2),
-    //     ..Default::default()
-    // });
-
-    commands.spawn()
-        .insert(BitmapText {
-            text: "PIZZA!".into(),
-            font: "Elecfont".into(),
-            padding: 6.,
-            // background_color: Color::rgb(0.1, 0.1, 0.2),
-            position: Vec3::new(0., 0., 2.),
-            ..Default::default()
-        })
-        .insert(Bounce {
-            effects: vec![
-                BounceEffect {
-                    effect_type: EffectType::Bounce,
-                    distance: 30.,
-                    period: 0.75,
-                    ..Default::default()
-                },
-                BounceEffect {
-                    effect_type: EffectType::HorizontalWave,
-                    distance: 50.,
-                    period: 2.,
-                    ..Default::default()
-                },
-            ],
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    // Widen the spaces so every wrapped line fills the box.
+    Justify,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextOverflow {
+    // Leave out the lines that don't fit.
+    Clip,
+    // Try each of these fonts in turn until the text fits, clipping it in the last one.
+    Shrink(Vec<String>),
+    // Cut the last line that fits short with an ellipsis.
+    Ellipsis,
+    // Show one boxful of lines at a time, with an indicator while there are more.
+    // Clicking the box turns to the next page.
+    Paginate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextFill {
+    Solid(Color),
+    // Blend from one colour at the top of each glyph to another at the bottom.
+    // Glyph rendering only uses the top colour.
+    Gradient(Color, Color),
+}
+impl TextFill {
+    // The fill's colour at a fraction of the way down a glyph.
+    fn color_at(&self, t: f32) -> Color {
+        match *self {
+            TextFill::Solid(color) => color,
+            TextFill::Gradient(top, bottom) => {
+                let top = Vec4::from(top.as_rgba_f32());
+                let bottom = Vec4::from(bottom.as_rgba_f32());
+                Color::from(top.lerp(bottom, t))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextShadow {
+    // How far the shadow is from the glyphs, with positive y upwards like the world.
+    pub offset: Vec2,
+    pub color: Color,
+}
+
+// Where the lines sit within a box taller than they need.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextRenderMode {
+    // Draw the whole text into one image, displayed on a single quad.
+    Image,
+    // Spawn a sprite for each character from the font's texture atlas, so they can move separately.
+    Glyphs,
+}
+
+// A sprite for one character of a BitmapText rendered as glyphs.
+#[derive(Component)]
+pub struct Glyph {
+    // The character's position in the laid out text, counting spaces.
+    pub char_index: usize,
+    // The glyph's translation relative to the text, before any effects are applied.
+    pub origin: Vec3,
+    // The glyph's colour from the text's markup, before any effects are applied.
+    pub color: Color,
+}
+
+// An image that can be placed in text with an icon tag, tinted with a colour.
+pub struct TextIcon {
+    pub image: Handle<Image>,
+    pub color: Color,
+}
+
+// Icons for text markup, by name.
+#[derive(Default)]
+pub struct TextIcons(pub HashMap<String, TextIcon>);
+
+// Where the centre of a BitmapText was last rendered and its size, and the image it was drawn
+// into if any, so that re-renders can reuse it. Also the characters last reported missing,
+// so that each text only warns about them once, and how many pages it has.
+#[derive(Component)]
+struct RenderedText {
+    center: Vec3,
+    size: Vec2,
+    image: Option<Handle<Image>>,
+    missing_chars: Vec<char>,
+    page_count: usize,
+}
+
+// The font a character's glyph comes from, its index in that font's texture atlas,
+// its metrics, and the position of its top left corner within the text box.
+struct PlacedGlyph<'a> {
+    font: &'a BitmapFont,
+    atlas_index: usize,
+    char_index: usize,
+    metrics: GlyphMetrics,
+    position: Vec2,
+    color: Option<Color>,
+}
+
+// An icon's name, and the position of its top left corner and its size within the text box.
+struct PlacedIcon {
+    name: String,
+    position: Vec2,
+    size: f32,
+}
+
+struct TextLayout<'a> {
+    size: Vec2,
+    glyphs: Vec<PlacedGlyph<'a>>,
+    icons: Vec<PlacedIcon>,
+    // Whether there were more lines than fit in the box.
+    overflowed: bool,
+    page_count: usize,
+}
+
+const ELLIPSIS: &str = "...";
+// Drawn in the bottom right corner of a paginated text box while there are more pages.
+const MORE_INDICATOR: char = '>';
+
+// A unit quad shared by all rendered text, which is scaled to each text's box size.
+struct TextMesh(Mesh2dHandle);
+
+// A line of laid out text, whether it was wrapped rather than ending its paragraph,
+// and the markup colour of each of its characters.
+struct Line {
+    text: String,
+    wrapped: bool,
+    colors: Vec<Option<Color>>,
+}
+
+// Split text into lines at each newline, then wrap them at spaces to the width that
+// line_width gives for each line's index. Words too wide for a line of their own are broken
+// between characters, with a hyphen at each break if hyphenate is set.
+// A paragraph isn't wrapped if the width of its first line isn't positive.
+fn wrap_lines(
+    text: &str,
+    line_width: impl Fn(usize) -> f32,
+    hyphenate: bool,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<Line> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n').map(|p| p.trim_end_matches('\r')) {
+        if line_width(lines.len()) <= 0. {
+            lines.push(Line {
+                text: paragraph.into(),
+                wrapped: false,
+                colors: vec![],
+            });
+            continue;
+        }
+
+        let mut line: Option<String> = None;
+        let mut broken = false;
+        for word in paragraph.split(' ') {
+            if let Some(current) = line.take() {
+                let extended = format!("{} {}", current, word);
+                if measure(&extended) <= line_width(lines.len()) {
+                    line = Some(extended);
+                    continue;
+                }
+                // This word doesn't fit; end the line before it.
+                lines.push(Line {
+                    text: current.trim_end().into(),
+                    wrapped: true,
+                    colors: vec![],
+                });
+                broken = true;
+            }
+            // Don't start a line with the spaces the previous one was broken at.
+            if word.is_empty() && broken {
+                continue;
+            }
+
+            if measure(word) <= line_width(lines.len()) {
+                line = Some(word.into());
+            } else {
+                // Break the word a line at a time, as each line can have its own width.
+                let mut rest: String = word.into();
+                loop {
+                    let mut pieces = break_word(&rest, line_width(lines.len()), hyphenate,
+                        &measure);
+                    if pieces.len() == 1 {
+                        line = pieces.pop();
+                        break;
+                    }
+                    let piece = pieces.remove(0);
+                    let taken = piece.chars().count() - hyphenate as usize;
+                    rest = rest.chars().skip(taken).collect();
+                    lines.push(Line {
+                        text: piece,
+                        wrapped: true,
+                        colors: vec![],
+                    });
+                }
+            }
+        }
+
+        lines.push(Line {
+            text: line.unwrap_or_default(),
+            wrapped: false,
+            colors: vec![],
         });
+    }
+
+    lines
+}
+
+// Give each character of the wrapped lines the colour of the character it came from,
+// skipping the spaces and newlines that lines were broken at.
+// Hyphens added at breaks take the colour of the character before them.
+fn color_lines(lines: &mut [Line], rich: &RichText) {
+    let mut source = rich.text.chars().zip(rich.colors.iter().copied()).peekable();
+    let mut last_color = None;
+    for line in lines.iter_mut() {
+        line.colors = line.text.chars()
+            .map(|c| {
+                while let Some(&(s, _)) = source.peek() {
+                    if s == c || !s.is_whitespace() {
+                        break;
+                    }
+                    source.next();
+                }
+                if let Some(&(s, color)) = source.peek() {
+                    if s == c {
+                        source.next();
+                        last_color = color;
+                    }
+                }
+                last_color
+            })
+            .collect();
+    }
+}
+
+// Cut a line short enough to end with an ellipsis and still fit in max_width.
+fn ellipsize(line: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> String {
+    let mut chars: Vec<char> = line.chars().collect();
+    loop {
+        let ellipsized = format!("{}{}", chars.iter().collect::<String>().trim_end(), ELLIPSIS);
+        if chars.is_empty() || measure(&ellipsized) <= max_width {
+            return ellipsized;
+        }
+        chars.pop();
+    }
+}
+
+// Break a word into pieces no wider than max_width, leaving at least one character in each.
+fn break_word(
+    word: &str,
+    max_width: f32,
+    hyphenate: bool,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<String> {
+    let hyphen = if hyphenate { "-" } else { "" };
+
+    let chars: Vec<char> = word.chars().collect();
+    let mut pieces = vec![];
+    let mut piece = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        // The last piece has no hyphen after it.
+        let suffix = if i + 1 < chars.len() { hyphen } else { "" };
+        if !piece.is_empty() && measure(&format!("{}{}{}", piece, c, suffix)) > max_width {
+            pieces.push(std::mem::take(&mut piece) + hyphen);
+        }
+        piece.push(c);
+    }
+    pieces.push(piece);
+
+    pieces
 }
 
-fn wrap_lines(text: &str, maxlen: usize) -> Vec<&str> {
-    let mut lines: Vec<&str> = vec![];
-    if maxlen <= 0 {
-        lines.push(&text);
+fn create_text_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.insert_resource(TextMesh(meshes.add(Mesh::from(shape::Quad::default())).into()));
+}
+
+fn layout_text<'a>(
+    text: &BitmapText,
+    rich: &RichText,
+    fonts: &FontChain<'a>,
+) -> TextLayout<'a> {
+    let font = fonts.primary();
+    let max_width = if text.box_size.x > 0. {
+        text.box_size.x - text.padding * 2.
     } else {
-        let mut linestart = 0;
-        let mut lineend = 0;
-        for (i, char) in text.chars().enumerate() {
-            if char == ' ' {
-                if i - linestart <= maxlen {
-                    // Line not full yet; mark this space and keep going.
-                    lineend = i + 1;
-                } else if lineend == linestart {
-                    // Line full with a single word; push everything up to here.
-                    lineend = i + 1;
-                    lines.push(&text[linestart..lineend - 1]);
-                    linestart = lineend;
-                } else {
-                    // Line full with more than one word;
-                    // push up to the end of the last one.
-                    lines.push(&text[linestart..lineend - 1]);
-                    linestart = lineend;
-                    lineend = i + 1;
+        f32::INFINITY
+    };
+    let mut lines = wrap_lines(&rich.text, |_| max_width, text.hyphenate,
+        |line| fonts.measure(line));
+
+    // Fit as many lines as the box has room for, always showing at least one.
+    let max_lines = if text.box_size.y > 0. {
+        let room = text.box_size.y - text.padding * 2. - font.info.tile_size.y;
+        (room / font.info.line_height).floor().max(0.) as usize + 1
+    } else {
+        lines.len()
+    };
+
+    // Pages are ended with the more indicator, so leave room for it on each page's last line.
+    if text.overflow == TextOverflow::Paginate && lines.len() > max_lines {
+        let reserved = fonts.measure(&MORE_INDICATOR.to_string()) + font.info.letter_spacing;
+        let page_width = |index: usize| if index % max_lines == max_lines - 1 {
+            max_width - reserved
+        } else {
+            max_width
+        };
+        lines = wrap_lines(&rich.text, page_width, text.hyphenate, |line| fonts.measure(line));
+    }
+    color_lines(&mut lines, rich);
+    let overflowed = lines.len() > max_lines;
+    let page_count = match text.overflow {
+        TextOverflow::Paginate => lines.len().div_ceil(max_lines),
+        _ => 1,
+    };
+    let page = text.page.min(page_count - 1);
+
+    if overflowed {
+        match &text.overflow {
+            TextOverflow::Clip | TextOverflow::Shrink(_) => lines.truncate(max_lines),
+            TextOverflow::Ellipsis => {
+                lines.truncate(max_lines);
+                if let Some(last) = lines.last_mut() {
+                    last.text = ellipsize(&last.text, max_width, |line| fonts.measure(line));
+                    last.wrapped = false;
+                    // The ellipsis takes the colour of the last character kept.
+                    last.colors.truncate(last.text.chars().count() - ELLIPSIS.chars().count());
                 }
             }
+            TextOverflow::Paginate => {
+                lines.drain(..page * max_lines);
+                lines.truncate(max_lines);
+            }
+        }
+    }
+
+    // Fit the box to the text on any axis that isn't given.
+    let lines_height = font.info.tile_size.y + font.info.line_height * (lines.len() - 1) as f32;
+    let size = Vec2::new(
+        if text.box_size.x > 0. { text.box_size.x } else {
+            lines.iter().map(|line| fonts.measure(&line.text)).fold(0., f32::max)
+                + text.padding * 2.
+        },
+        if text.box_size.y > 0. { text.box_size.y } else { lines_height + text.padding * 2. },
+    );
+
+    let inner_size = size - Vec2::splat(text.padding * 2.);
+    let top = text.padding + match text.vertical_align {
+        VerticalAlign::Top => 0.,
+        VerticalAlign::Middle => ((inner_size.y - lines_height) / 2.).round(),
+        VerticalAlign::Bottom => inner_size.y - lines_height,
+    };
+
+    let mut glyphs = vec![];
+    let mut icons = vec![];
+    let mut char_index = 0;
+    for (y, line) in lines.iter().enumerate() {
+        let line_top = top + font.info.line_height * y as f32;
+        let free_width = inner_size.x - fonts.measure(&line.text);
+        let mut x = text.padding + match text.align {
+            TextAlign::Left | TextAlign::Justify => 0.,
+            TextAlign::Center => (free_width / 2.).round(),
+            TextAlign::Right => free_width,
+        };
+
+        // Share the free width between the spaces, leaving the ends of paragraphs ragged.
+        let spaces = line.text.matches(' ').count();
+        let space_width = match text.align {
+            TextAlign::Justify if line.wrapped && spaces > 0 =>
+                free_width / spaces as f32,
+            _ => 0.,
+        };
+
+        for (i, c) in line.text.chars().enumerate() {
+            let (glyph, metrics) = fonts.resolve(c);
+            if let Some(name) = rich.icon(c) {
+                icons.push(PlacedIcon {
+                    name: name.into(),
+                    position: Vec2::new(x.round(), line_top),
+                    size: metrics.advance,
+                });
+            } else if let Some((glyph_font, atlas_index)) = glyph {
+                glyphs.push(PlacedGlyph {
+                    font: glyph_font,
+                    atlas_index,
+                    char_index,
+                    metrics,
+                    position: Vec2::new(x.round(), line_top) + metrics.offset,
+                    color: line.colors.get(i).or_else(|| line.colors.last()).copied().flatten(),
+                });
+            }
+            x += metrics.advance + font.info.letter_spacing;
+            if c == ' ' {
+                x += space_width;
+            }
+            char_index += 1;
         }
-        if lineend > linestart && text.len() - linestart > maxlen {
-            // End of text with more than one word remaining
-            // and longer than a line; push up to the end of the last word.
-            lines.push(&text[linestart..lineend - 1]);
-            linestart = lineend;
+    }
+
+    if page + 1 < page_count {
+        let (glyph, metrics) = fonts.resolve(MORE_INDICATOR);
+        if let Some((glyph_font, atlas_index)) = glyph {
+            glyphs.push(PlacedGlyph {
+                font: glyph_font,
+                atlas_index,
+                char_index,
+                metrics,
+                position: Vec2::new(
+                    size.x - text.padding - metrics.advance,
+                    size.y - text.padding - font.info.tile_size.y,
+                ) + metrics.offset,
+                color: None,
+            });
         }
-        // Push the remaining text.
-        lines.push(&text[linestart..]);
     }
 
-    lines
+    TextLayout {
+        size,
+        glyphs,
+        icons,
+        overflowed,
+        page_count,
+    }
 }
 
-fn get_char_index(char: char, char_map: &CharMap) -> Option<usize> {
-    match char_map {
-        CharMap::Ascii => " !\"#$%&'()*+'-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~",
-        CharMap::TSK => "ABCDEFGHIJKLMNOPQRSTUVWXYZ.,()0123456789",
-    }.find(char)
+// Lay out the text in its own font or, if it has to shrink to fit,
+// in the first of its fallback sizes that fits.
+fn layout_text_to_fit<'a>(
+    text: &BitmapText,
+    rich: &RichText,
+    find_font: impl Fn(&str) -> Option<&'a BitmapFont>,
+) -> (FontChain<'a>, TextLayout<'a>) {
+    let mut font_chain = FontChain::new(&text.font, &find_font);
+    let mut layout = layout_text(text, rich, &font_chain);
+
+    if let TextOverflow::Shrink(smaller_fonts) = &text.overflow {
+        for name in smaller_fonts.iter() {
+            if !layout.overflowed {
+                break;
+            }
+            font_chain = FontChain::new(name, &find_font);
+            layout = layout_text(text, rich, &font_chain);
+        }
+    }
+
+    (font_chain, layout)
 }
 
+// The images that text refers to through handles, copied out of Bevy's asset storage,
+// or loaded directly when rendering without Bevy.
+#[derive(Default)]
+pub struct TextImages {
+    // Each icon's image by name, already tinted with the icon's colour.
+    pub icons: HashMap<String, RgbaImage>,
+    // The border image of the text's background panel.
+    pub background: Option<RgbaImage>,
+}
+
+// Lay out and draw text to an image, finding its font and fallback fonts by name.
+// This doesn't need Bevy, so it can be used by tools and tests.
+pub fn render_text_image(
+    text: &BitmapText,
+    fonts: &[BitmapFont],
+    images: &TextImages,
+) -> RgbaImage {
+    let rich = RichText::parse(&text.text);
+    let (_, layout) = layout_text_to_fit(text, &rich,
+        |name| fonts.iter().find(|f| f.info.name == name));
+    draw_text(text, &layout, images)
+}
+
+// Overlay an image at a position that may be negative, clipping whatever is off the left or top.
+// Glyphs end up there when a line is wider than its box, or a glyph has a negative offset.
+fn overlay_clipped(bottom: &mut RgbaImage, top: &RgbaImage, position: Vec2) {
+    let (x, y) = (position.x.floor() as i64, position.y.floor() as i64);
+    let (clip_x, clip_y) = ((-x).max(0) as u32, (-y).max(0) as u32);
+    if clip_x >= top.width() || clip_y >= top.height() {
+        return;
+    }
+
+    let visible = top.view(clip_x, clip_y, top.width() - clip_x, top.height() - clip_y);
+    overlay(bottom, &visible.to_image(), x.max(0) as u32, y.max(0) as u32);
+}
+
+// Multiply an image's colours by a tint.
+fn tint_image(image: &mut RgbaImage, tint: Color) {
+    let tint = tint.as_rgba_f32();
+    for pixel in image.pixels_mut() {
+        for (channel, t) in pixel.0.iter_mut().zip(tint.iter()) {
+            *channel = (*channel as f32 * t).round() as u8;
+        }
+    }
+}
+
+// Copy an icon's image out of its texture, tinted with its colour.
+// Returns None if the image hasn't loaded yet.
+fn get_icon_image(icon: &TextIcon, images: &Assets<Image>) -> Option<RgbaImage> {
+    let mut image = from_texture(images.get(&icon.image)?)?;
+    tint_image(&mut image, icon.color);
+    Some(image)
+}
+
+// Draw the text's background, panel, glyphs and icons into a new image, the size of the text box.
+fn draw_text(
+    text: &BitmapText,
+    layout: &TextLayout,
+    images: &TextImages,
+) -> RgbaImage {
+    let (width, height) = (layout.size.x as u32, layout.size.y as u32);
+
+    // Draw the glyphs and icons onto a transparent layer first,
+    // so that the outline and shadow can be made from its shape.
+    let mut layer = RgbaImage::new(width, height);
+
+    // Overlay each character from its font's atlas image onto the layer.
+    for glyph in layout.glyphs.iter() {
+        if let Some(rect) = glyph.font.rects.get(glyph.atlas_index) {
+            let mut glyph_image = glyph.font.atlas_image.view(
+                (rect.min.x + glyph.metrics.left) as u32,
+                rect.min.y as u32,
+                glyph.metrics.width as u32,
+                rect.height() as u32,
+            ).to_image();
+            if let Some(color) = glyph.color {
+                tint_image(&mut glyph_image, color);
+            }
+            fill_image(&mut glyph_image, &text.fill);
+
+            overlay_clipped(&mut layer, &glyph_image, glyph.position);
+        }
+    }
+
+    for icon in layout.icons.iter() {
+        if let Some(icon_image) = images.icons.get(&icon.name) {
+            let icon_image = resize(icon_image, icon.size as u32, icon.size as u32,
+                FilterType::Nearest);
+            overlay_clipped(&mut layer, &icon_image, icon.position);
+        }
+    }
+
+    if let Some(color) = text.outline {
+        layer = outline_image(&layer, color);
+    }
+
+    // Create a new image with the given dimensions and background colour.
+    let mut image = RgbaImage::from_pixel(width, height, color_to_rgba(text.background_color));
+    if let Some(background) = draw_background(text, images, width, height) {
+        overlay(&mut image, &background, 0, 0);
+    }
+    if let Some(shadow) = &text.shadow {
+        overlay(&mut image, &shadow_image(&layer, shadow), 0, 0);
+    }
+    overlay(&mut image, &layer, 0, 0);
+
+    image
+}
+
+// The text's background panel at the size of the box, if it has one and its image is loaded.
+fn draw_background(text: &BitmapText, images: &TextImages, width: u32, height: u32)
+    -> Option<RgbaImage> {
+    let slice = text.background.as_ref()?;
+    let source = images.background.as_ref()?;
+    Some(draw_nine_slice(source, slice, width, height))
+}
+
+fn color_to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([
+        (color.r() * 255.) as u8,
+        (color.g() * 255.) as u8,
+        (color.b() * 255.) as u8,
+        (color.a() * 255.) as u8,
+    ])
+}
+
+// Multiply a glyph's colours by the text's fill, from the top of the glyph to the bottom.
+fn fill_image(image: &mut RgbaImage, fill: &TextFill) {
+    let height = image.height();
+    for (_, y, pixel) in image.enumerate_pixels_mut() {
+        let t = if height > 1 { y as f32 / (height - 1) as f32 } else { 0. };
+        let tint = fill.color_at(t).as_rgba_f32();
+        for (channel, t) in pixel.0.iter_mut().zip(tint.iter()) {
+            *channel = (*channel as f32 * t).round() as u8;
+        }
+    }
+}
+
+// Surround the opaque parts of an image with a one pixel line, including diagonally.
+fn outline_image(image: &RgbaImage, color: Color) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let outline_pixel = color_to_rgba(color);
+
+    let mut outlined = RgbaImage::new(width, height);
+    for (x, y, pixel) in outlined.enumerate_pixels_mut() {
+        let near_glyph = (-1..=1).any(|dx: i32| (-1..=1).any(|dy: i32| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            nx >= 0 && ny >= 0 && (nx as u32) < width && (ny as u32) < height
+                && image.get_pixel(nx as u32, ny as u32)[3] > 0
+        }));
+        if near_glyph {
+            *pixel = outline_pixel;
+        }
+    }
+
+    overlay(&mut outlined, image, 0, 0);
+    outlined
+}
+
+// A copy of an image's shape in the shadow's colour, moved by the shadow's offset.
+fn shadow_image(image: &RgbaImage, shadow: &TextShadow) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut shadow_pixel = color_to_rgba(shadow.color);
+    let shadow_alpha = shadow_pixel[3] as f32 / 255.;
+
+    // Images count y downwards, so flip the offset's y.
+    let (dx, dy) = (shadow.offset.x.round() as i32, -shadow.offset.y.round() as i32);
+
+    let mut shadowed = RgbaImage::new(width, height);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let (sx, sy) = (x as i32 + dx, y as i32 + dy);
+        if pixel[3] > 0 && sx >= 0 && sy >= 0 && (sx as u32) < width && (sy as u32) < height {
+            shadow_pixel[3] = (pixel[3] as f32 * shadow_alpha).round() as u8;
+            shadowed.put_pixel(sx as u32, sy as u32, shadow_pixel);
+        }
+    }
+
+    shadowed
+}
+
+#[allow(clippy::type_complexity)]
 fn render_text(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<CycleMaterial>>,
+    text_mesh: Res<TextMesh>,
+    text_icons: Res<TextIcons>,
     fonts: Query<&BitmapFont>,
-    texts: Query<(&BitmapText, Entity), Added<BitmapText>>,
+    mut texts: Query<(
+        Entity,
+        &BitmapText,
+        Option<&mut RenderedText>,
+        Option<&mut Transform>,
+        Option<&Handle<CycleMaterial>>,
+    ), Changed<BitmapText>>,
 ) {
-    for (text, entity) in texts.iter() {
-        let font = fonts.iter().find(|f| f.info.name == text.font).expect("Font not found.");
-
-        let lines = wrap_lines(&text.text,
-            ((text.box_size.x - text.padding * 2.) / font.info.tile_size.x) as usize);
-
-        let box_size = if text.box_size != Vec2::ZERO { text.box_size } else {
-            let clen = lines.iter().map(|line| line.len()).max().unwrap_or(0) as f32;
-            Vec2::new(
-                clen * font.info.tile_size.x + text.padding * 2.,
-                font.info.tile_size.y + text.padding * 2.,
-            )
+    for (entity, text, rendered, transform, material_handle) in texts.iter_mut() {
+        let rich = RichText::parse(&text.text);
+        let (font_chain, layout) = layout_text_to_fit(text, &rich,
+            |name| fonts.iter().find(|f| f.info.name == name));
+
+        // Warn about missing characters once, rather than every time the text changes.
+        let missing_chars = font_chain.missing_chars(&rich.text);
+        if !missing_chars.is_empty()
+            && rendered.as_ref().is_none_or(|r| r.missing_chars != missing_chars) {
+            warn!("Font {} has no glyphs for {:?} in text {:?}.",
+                font_chain.primary().info.name, missing_chars.iter().collect::<String>(),
+                text.text);
+        }
+
+        // The anchor is given from -0.5 to 0.5 across the box, so move the centre away from it.
+        let center = text.position - (text.anchor.as_vec() * layout.size).extend(0.);
+
+        // Keep any offset added by effects like Bounce, moving only by the change in position.
+        let translation = match (&rendered, &transform) {
+            (Some(rendered), Some(transform)) =>
+                transform.translation - rendered.center + center,
+            _ => center,
         };
 
-        // Create a new image with the given dimensions and background colour.
-        let mut image = RgbaImage::from_pixel(
-            box_size.x as u32,
-            box_size.y as u32,
-            Rgba([
-                (text.background_color.r() * 255.) as u8,
-                (text.background_color.g() * 255.) as u8,
-                (text.background_color.b() * 255.) as u8,
-                (text.background_color.a() * 255.) as u8,
-            ]),
-        );
-
-        // Overlay each character from the texture atlas onto the image.
-        let texture_atlas = texture_atlases.get(&font.texture_atlas_handle)
-            .expect("Texture atlas not found.");
-        let atlas_tex = images.get(&texture_atlas.texture)
-            .expect("Texture atlas texture not found.");
-        let atlas_image = RgbaImage::from_raw(
-            texture_atlas.size.x as u32,
-            texture_atlas.size.y as u32,
-            atlas_tex.data.clone(),
-        ).expect("Atlas image not found.");
-
-        for (y, line) in lines.iter().enumerate() {
-            let offset_y = text.padding + font.info.tile_size.y * y as f32;
-
-            for (x, c) in line.char_indices() {
-                if let Some(index) = get_char_index(c, &font.info.char_map) {
-                    if let Some(rect) = texture_atlas.textures.get(index) {
-                        let view = atlas_image.view(
-                            rect.min.x as u32,
-                            rect.min.y as u32,
-                            rect.width() as u32,
-                            rect.height() as u32,
-                        );
-
-                        overlay(&mut image, &view,
-                            (text.padding + font.info.tile_size.x * x as f32) as u32,
-                            offset_y as u32);
+        let text_images = TextImages {
+            icons: layout.icons.iter()
+                .filter_map(|icon| {
+                    let text_icon = text_icons.0.get(&icon.name)?;
+                    Some((icon.name.clone(), get_icon_image(text_icon, &images)?))
+                })
+                .collect(),
+            background: text.background.as_ref()
+                .and_then(|b| from_texture(images.get(&b.image)?)),
+        };
+
+        let mut entity_commands = commands.entity(entity);
+
+        match text.render_mode {
+            TextRenderMode::Image => {
+                let image = draw_text(text, &layout, &text_images);
+                let size = Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    ..Default::default()
+                };
+
+                let existing_image = rendered.as_ref().and_then(|r| r.image.clone());
+                let image_handle = match (existing_image, transform, material_handle) {
+                    (Some(image_handle), Some(mut transform), Some(material_handle)) => {
+                        // Already rendered: draw into the existing image,
+                        // resizing it if the box has changed.
+                        let texture = images.get_mut(&image_handle).expect("Text image not found.");
+                        if texture.texture_descriptor.size != size {
+                            texture.resize(size);
                         }
-                }
+                        texture.data = image.into_raw();
+
+                        // Touch the material too, so it picks up the re-uploaded texture.
+                        if let Some(material) = materials.get_mut(material_handle) {
+                            material.enabled = text.cycle as u32;
+                            material.settings = text.cycle_settings;
+                            // Keep how far the material has animated, so it doesn't jump back.
+                            material.time = CycleTime {
+                                elapsed: material.time.elapsed,
+                                ..text.cycle_time
+                            };
+                        }
+
+                        transform.scale = Vec3::from((layout.size, 1.));
+                        transform.translation = translation;
+
+                        image_handle
+                    }
+                    _ => {
+                        // Convert the image to a texture asset.
+                        let texture = images.add(Image::new(
+                            size,
+                            TextureDimension::D2,
+                            image.into_raw(),
+                            TextureFormat::Rgba8UnormSrgb,
+                        ));
+
+                        // Add texture as a 2D mesh onto the existing BitmapText entity,
+                        // replacing any glyphs it was previously rendered as.
+                        entity_commands.despawn_descendants();
+                        entity_commands
+                            .insert_bundle(MaterialMesh2dBundle {
+                                mesh: text_mesh.0.clone(),
+                                transform: Transform {
+                                    scale: Vec3::from((layout.size, 1.)),
+                                    translation,
+                                    ..Default::default()
+                                },
+                                material: materials.add(CycleMaterial {
+                                    image: Some(texture.clone()),
+                                    enabled: text.cycle as u32,
+                                    settings: text.cycle_settings,
+                                    time: text.cycle_time,
+                                    ..Default::default()
+                                }),
+                                ..Default::default()
+                            });
+
+                        texture
+                    }
+                };
+
+                entity_commands.insert(RenderedText {
+                    center,
+                    size: layout.size,
+                    image: Some(image_handle),
+                    missing_chars,
+                    page_count: layout.page_count,
+                });
+            }
+            TextRenderMode::Glyphs => {
+                let background_texture = draw_background(text, &text_images,
+                    layout.size.x as u32, layout.size.y as u32)
+                    .map(|image| images.add(to_texture(image)));
+
+                // Replace any previous glyphs or image with a sprite for each character.
+                entity_commands.despawn_descendants();
+                entity_commands
+                    .remove::<Handle<CycleMaterial>>()
+                    .remove::<Mesh2dHandle>()
+                    .insert_bundle(SpatialBundle {
+                        transform: Transform::from_translation(translation),
+                        ..Default::default()
+                    })
+                    .insert(RenderedText {
+                        center,
+                        size: layout.size,
+                        image: None,
+                        missing_chars,
+                        page_count: layout.page_count,
+                    })
+                    .with_children(|parent| {
+                        let origin = Vec2::new(-layout.size.x / 2., layout.size.y / 2.);
+
+                        if text.background_color.a() > 0. {
+                            parent.spawn_bundle(SpriteBundle {
+                                sprite: Sprite {
+                                    color: text.background_color,
+                                    custom_size: Some(layout.size),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            });
+                        }
+                        if let Some(texture) = background_texture {
+                            parent.spawn_bundle(SpriteBundle {
+                                texture,
+                                transform: Transform::from_xyz(0., 0., 0.005),
+                                ..Default::default()
+                            });
+                        }
+
+                        for glyph in layout.glyphs.iter() {
+                            if let Some(rect) = glyph.font.rects.get(glyph.atlas_index) {
+                                let glyph_size = rect.size();
+                                let glyph_origin = Vec3::new(
+                                    origin.x + glyph.position.x - glyph.metrics.left
+                                        + glyph_size.x / 2.,
+                                    origin.y - glyph.position.y - glyph_size.y / 2.,
+                                    0.01,
+                                );
+                                let color = Color::from(
+                                    Vec4::from(glyph.color.unwrap_or(Color::WHITE).as_rgba_f32())
+                                        * Vec4::from(text.fill.color_at(0.).as_rgba_f32()));
+                                parent
+                                    .spawn_bundle(SpriteSheetBundle {
+                                        sprite: TextureAtlasSprite {
+                                            index: glyph.atlas_index,
+                                            color,
+                                            ..Default::default()
+                                        },
+                                        texture_atlas: glyph.font.texture_atlas_handle.clone(),
+                                        transform: Transform::from_translation(glyph_origin),
+                                        ..Default::default()
+                                    })
+                                    .insert(Glyph {
+                                        char_index: glyph.char_index,
+                                        origin: glyph_origin,
+                                        color,
+                                    });
+                            }
+                        }
+
+                        for icon in layout.icons.iter() {
+                            if let Some(text_icon) = text_icons.0.get(&icon.name) {
+                                parent.spawn_bundle(SpriteBundle {
+                                    sprite: Sprite {
+                                        color: text_icon.color,
+                                        custom_size: Some(Vec2::splat(icon.size)),
+                                        ..Default::default()
+                                    },
+                                    texture: text_icon.image.clone(),
+                                    transform: Transform::from_xyz(
+                                        origin.x + icon.position.x + icon.size / 2.,
+                                        origin.y - icon.position.y - icon.size / 2.,
+                                        0.01,
+                                    ),
+                                    ..Default::default()
+                                });
+                            }
+                        }
+                    });
+            }
+        }
+    }
+}
+
+// Turn a paginated text to its next page when its box is clicked.
+fn turn_pages(
+    mut click_events: EventReader<MouseButtonInput>,
+    windows: Res<Windows>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    mut texts: Query<(&mut BitmapText, &RenderedText)>,
+) {
+    if !click_events.iter().any(|e| {
+        e.button == MouseButton::Left && e.state == ButtonState::Released
+    }) {
+        return;
+    }
+
+    if let Some(position) = cursor::get_world_coords(windows, cameras) {
+        for (mut text, rendered) in texts.iter_mut() {
+            if text.overflow != TextOverflow::Paginate || text.page + 1 >= rendered.page_count {
+                continue;
+            }
+
+            let diff = (rendered.center.xy() - position).abs();
+            if diff.x < rendered.size.x / 2. && diff.y < rendered.size.y / 2. {
+                text.page += 1;
             }
         }
+    }
+}
 
-        // Convert the image to a texture asset.
-        let texture = Image::new(
-            Extent3d {
-                width: box_size.x as u32,
-                height: box_size.y as u32,
-                ..Default::default()
-            },
-            TextureDimension::D2,
-            image.into_raw(),
-            TextureFormat::Rgba8UnormSrgb,
-        );
-
-        // Add texture as a 2D mesh onto the existing BitmapText entity.
-        commands.entity(entity)
-            .insert_bundle(MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    scale: Vec3::from((box_size, 1.)),
-                    translation: text.position,
-                    ..Default::default()
-                },
-                material: materials.add(CycleMaterial {
-                    image: Some(images.add(texture)),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One unit per character, so widths are easy to count.
+    fn count(line: &str) -> f32 {
+        line.chars().count() as f32
+    }
+
+    fn wrap(text: &str, max_width: f32, hyphenate: bool) -> Vec<String> {
+        wrap_lines(text, |_| max_width, hyphenate, count).into_iter()
+            .map(|line| line.text)
+            .collect()
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(wrap("one two three", 8., false), ["one two", "three"]);
+    }
+
+    #[test]
+    fn marks_wrapped_lines() {
+        let wrapped: Vec<bool> = wrap_lines("one two\nthree", |_| 5., false, count)
+            .iter().map(|line| line.wrapped).collect();
+        assert_eq!(wrapped, [true, false, false]);
+    }
+
+    #[test]
+    fn breaks_at_newlines() {
+        assert_eq!(wrap("one\ntwo\n\nthree", 20., false), ["one", "two", "", "three"]);
+    }
+
+    #[test]
+    fn breaks_at_windows_newlines() {
+        assert_eq!(wrap("one\r\ntwo\r\n", 20., false), ["one", "two", ""]);
+    }
+
+    #[test]
+    fn measures_multibyte_text_by_character() {
+        assert_eq!(wrap("åäö ÅÄÖ ¿qué?", 7., false), ["åäö ÅÄÖ", "¿qué?"]);
+        assert_eq!(wrap("ÄÅÖÄÅÖ", 4., false), ["ÄÅÖÄ", "ÅÖ"]);
+    }
+
+    #[test]
+    fn breaks_long_words_between_characters() {
+        assert_eq!(wrap("ab abcdefghij", 4., false), ["ab", "abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn hyphenates_long_words() {
+        assert_eq!(wrap("abcdefghij", 4., true), ["abc-", "def-", "ghij"]);
+    }
+
+    #[test]
+    fn wraps_each_line_to_its_own_width() {
+        let lines: Vec<String> = wrap_lines("one two three four", |i| [7., 3.][i % 2], false, count)
+            .into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, ["one two", "thr", "ee four"]);
+    }
+
+    #[test]
+    fn breaks_long_words_to_each_lines_width() {
+        let lines: Vec<String> = wrap_lines("abcdefghij", |i| [3., 5.][i % 2], true, count)
+            .into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, ["ab-", "cdef-", "gh-", "ij"]);
+    }
+
+    #[test]
+    fn continues_after_a_broken_word() {
+        assert_eq!(wrap("abcdefg hi", 4., false), ["abcd", "efg", "hi"]);
+    }
+
+    #[test]
+    fn keeps_double_spaces_within_a_line() {
+        assert_eq!(wrap("one  two", 20., false), ["one  two"]);
+    }
+
+    #[test]
+    fn drops_double_spaces_at_a_break() {
+        assert_eq!(wrap("one  two", 4., false), ["one", "two"]);
+        assert_eq!(wrap("abcd   ef", 4., false), ["abcd", "ef"]);
+    }
+
+    #[test]
+    fn keeps_leading_spaces_of_a_paragraph() {
+        assert_eq!(wrap("  one", 20., false), ["  one"]);
+    }
+
+    #[test]
+    fn doesnt_wrap_without_a_width() {
+        assert_eq!(wrap("one two three", 0., false), ["one two three"]);
+        assert_eq!(wrap("one two\nthree", -5., true), ["one two", "three"]);
+    }
+
+    #[test]
+    fn wraps_with_variable_widths() {
+        // Narrow i and l, as in a variable-width font.
+        let measure = |line: &str| line.chars()
+            .map(|c| if c == 'i' || c == 'l' { 1. } else { 3. })
+            .sum::<f32>();
+        let lines: Vec<String> = wrap_lines("ill will mow", |_| 12., false, measure)
+            .into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, ["ill will", "mow"]);
+    }
+
+    #[test]
+    fn breaks_words_with_variable_widths() {
+        let measure = |line: &str| line.chars()
+            .map(|c| if c == 'i' || c == '-' { 1. } else { 3. })
+            .sum::<f32>();
+        assert_eq!(break_word("iiiiiiw", 4., false, measure), ["iiii", "ii", "w"]);
+        assert_eq!(break_word("iiiiiiw", 4., true, measure), ["iii-", "iii-", "w"]);
+    }
+
+    #[test]
+    fn leaves_a_character_on_each_piece() {
+        // Even when a single character is wider than the line.
+        assert_eq!(break_word("abc", 0.5, true, count), ["a-", "b-", "c"]);
+    }
+
+    // A blank font with A, B and ?, but no space, on 4 by 4 tiles.
+    fn font_without_space() -> BitmapFont {
+        let descriptor = FontDescriptor::from_json(br#"{
+            "name": "Test",
+            "image": "test.png",
+            "tile_size": [4, 4],
+            "grid_size": [3, 1],
+            "char_map": {"chars": "AB?"},
+            "fallback_char": "?"
+        }"#).expect("Font descriptor not parsed.");
+        BitmapFont::new(descriptor, RgbaImage::new(12, 4))
+    }
+
+    #[test]
+    fn draws_missing_characters_as_the_fallback() {
+        let font = font_without_space();
+        let chain = FontChain { fonts: vec![&font] };
+        assert_eq!(chain.resolve('C').0.map(|(_, index)| index), Some(2));
+    }
+
+    #[test]
+    fn leaves_missing_whitespace_blank() {
+        let font = font_without_space();
+        let chain = FontChain { fonts: vec![&font] };
+        let (glyph, metrics) = chain.resolve(' ');
+        assert!(glyph.is_none());
+        assert_eq!(metrics.advance, 2.);
+        assert_eq!(chain.measure("A B"), chain.measure("AB") + 2.);
     }
 }
@@ -0,0 +1,549 @@
+use anyhow::{anyhow, bail};
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    sprite::Rect,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+use std::path::Path;
+
+
+const ASCII_CHARS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+// How a descriptor lists the characters in its atlas.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CharMapSpec {
+    Ascii,
+    // One character per glyph, in atlas order.
+    Chars(String),
+    // One string per row of the grid, so rows can be left short.
+    Rows(Vec<String>),
+    // One Unicode code point per glyph, in atlas order.
+    Codepoints(Vec<u32>),
+}
+
+// Which case to try for characters that the font doesn't have.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseFold {
+    Upper,
+    Lower,
+}
+
+// The atlas index of each character a font has.
+#[derive(Clone, Debug)]
+pub struct CharMap {
+    indices: HashMap<char, usize>,
+    case_fold: Option<CaseFold>,
+}
+impl CharMap {
+    // Map characters to atlas indices. If a character appears twice, the first one is used.
+    pub fn new(
+        chars: impl IntoIterator<Item = (usize, char)>,
+        case_fold: Option<CaseFold>,
+    ) -> Self {
+        let mut indices = HashMap::default();
+        for (index, c) in chars {
+            indices.entry(c).or_insert(index);
+        }
+        CharMap {
+            indices,
+            case_fold,
+        }
+    }
+
+    // Map the characters a descriptor lists to the glyphs of a grid,
+    // failing if any would be past the last glyph.
+    fn from_spec(
+        spec: CharMapSpec,
+        columns: usize,
+        glyph_count: usize,
+        case_fold: Option<CaseFold>,
+    ) -> Result<Self, anyhow::Error> {
+        let chars: Vec<(usize, char)> = match spec {
+            CharMapSpec::Ascii => ASCII_CHARS.chars().enumerate().collect(),
+            CharMapSpec::Chars(chars) => chars.chars().enumerate().collect(),
+            CharMapSpec::Rows(rows) => {
+                if let Some(row) = rows.iter().find(|row| row.chars().count() > columns) {
+                    bail!("Char map row {:?} is longer than the grid's {} columns.", row, columns);
+                }
+                rows.iter().enumerate()
+                    .flat_map(|(y, row)| row.chars().enumerate()
+                        .map(move |(x, c)| (y * columns + x, c)))
+                    .collect()
+            }
+            CharMapSpec::Codepoints(codepoints) => codepoints.iter().enumerate()
+                .map(|(i, &codepoint)| char::from_u32(codepoint)
+                    .map(|c| (i, c))
+                    .ok_or_else(|| anyhow!("Invalid code point {}.", codepoint)))
+                .collect::<Result<_, _>>()?,
+        };
+
+        if let Some(&(index, c)) = chars.iter().find(|&&(index, _)| index >= glyph_count) {
+            bail!("Character {:?} is mapped to glyph {}, but the font has only {} glyphs.",
+                c, index, glyph_count);
+        }
+
+        Ok(CharMap::new(chars, case_fold))
+    }
+
+    // The character's atlas index, falling back to its other case if the font folds case.
+    pub fn index(&self, c: char) -> Option<usize> {
+        self.indices.get(&c).copied().or_else(|| {
+            let folded: String = match self.case_fold? {
+                CaseFold::Upper => c.to_uppercase().collect(),
+                CaseFold::Lower => c.to_lowercase().collect(),
+            };
+            // Skip characters that fold to more than one, like ß to SS.
+            let mut folded = folded.chars();
+            match (folded.next(), folded.next()) {
+                (Some(f), None) => self.indices.get(&f).copied(),
+                _ => None,
+            }
+        })
+    }
+
+    // Every mapped character with its atlas index, in atlas order.
+    pub fn chars(&self) -> Vec<(char, usize)> {
+        let mut chars: Vec<(char, usize)> = self.indices.iter().map(|(&c, &i)| (c, i)).collect();
+        chars.sort_by_key(|&(_, i)| i);
+        chars
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphWidths {
+    // Every glyph takes up its whole tile.
+    Fixed,
+    // Measure each glyph from the opaque columns of its tile in the atlas image.
+    Auto,
+    // A width for each glyph, in atlas order, drawn from the left of its tile.
+    Manual(Vec<f32>),
+    // Full metrics for each glyph, as given by a BMFont descriptor.
+    #[serde(skip)]
+    Metrics(Vec<GlyphMetrics>),
+}
+
+// Which part of a glyph's atlas rect to draw, where to draw it relative to the pen position,
+// and how far to move the pen afterwards.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphMetrics {
+    pub left: f32,
+    pub width: f32,
+    pub offset: Vec2,
+    pub advance: f32,
+}
+impl GlyphMetrics {
+    // Metrics for drawing columns of a tile side by side, with no offset.
+    pub fn columns(left: f32, width: f32) -> Self {
+        GlyphMetrics {
+            left,
+            width,
+            offset: Vec2::ZERO,
+            advance: width,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AtlasLayout {
+    // Tiles of tile_size, in this many columns and rows.
+    Grid(Vec2),
+    // Arbitrary rects, one for each glyph.
+    Rects(Vec<Rect>),
+}
+
+// Everything needed to turn an image into a font.
+#[derive(Clone, Debug)]
+pub struct FontDescriptor {
+    pub name: String,
+    // Path of the font's image, relative to the descriptor.
+    pub image: String,
+    pub tile_size: Vec2,
+    pub atlas: AtlasLayout,
+    pub char_map: CharMap,
+    pub glyph_widths: GlyphWidths,
+    // Extra space after each glyph.
+    pub letter_spacing: f32,
+    // Distance from the top of one line to the top of the next.
+    pub line_height: f32,
+    // The character drawn in place of any that no font in the chain has.
+    pub fallback_char: Option<char>,
+    // Names of fonts to take glyphs from when this one doesn't have them, in order.
+    pub fallback_fonts: Vec<String>,
+}
+
+// The JSON descriptor format, for fonts laid out on a grid.
+#[derive(Deserialize)]
+struct JsonFont {
+    name: String,
+    image: String,
+    tile_size: [f32; 2],
+    grid_size: [f32; 2],
+    char_map: CharMapSpec,
+    case_fold: Option<CaseFold>,
+    #[serde(default = "default_glyph_widths")]
+    glyph_widths: GlyphWidths,
+    #[serde(default)]
+    letter_spacing: f32,
+    line_height: Option<f32>,
+    fallback_char: Option<char>,
+    #[serde(default)]
+    fallback_fonts: Vec<String>,
+}
+
+fn default_glyph_widths() -> GlyphWidths { GlyphWidths::Fixed }
+
+impl FontDescriptor {
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let font: JsonFont = serde_json::from_slice(bytes)?;
+
+        Ok(FontDescriptor {
+            name: font.name,
+            image: font.image,
+            tile_size: font.tile_size.into(),
+            atlas: AtlasLayout::Grid(font.grid_size.into()),
+            char_map: CharMap::from_spec(
+                font.char_map,
+                font.grid_size[0] as usize,
+                (font.grid_size[0] * font.grid_size[1]) as usize,
+                font.case_fold,
+            )?,
+            glyph_widths: font.glyph_widths,
+            letter_spacing: font.letter_spacing,
+            line_height: font.line_height.unwrap_or(font.tile_size[1]),
+            fallback_char: font.fallback_char,
+            fallback_fonts: font.fallback_fonts,
+        })
+    }
+
+    // Read an AngelCode BMFont text descriptor. Only single page fonts are supported.
+    pub fn from_bmfont(source: &str) -> Result<Self, anyhow::Error> {
+        let mut name = None;
+        let mut image = None;
+        let mut line_height = None;
+        let mut atlas_size = None;
+        let mut chars = vec![];
+        let mut rects = vec![];
+        let mut metrics = vec![];
+
+        for line in source.lines() {
+            let (tag, attributes) = parse_bmfont_line(line);
+            let number = |key: &str| -> Result<f32, anyhow::Error> {
+                Ok(attributes.get(key)
+                    .ok_or_else(|| anyhow!("BMFont {} line is missing {}.", tag, key))?
+                    .parse()?)
+            };
+
+            match tag.as_str() {
+                "info" => {
+                    name = attributes.get("face").cloned();
+                }
+                "common" => {
+                    line_height = Some(number("lineHeight")?);
+                    atlas_size = Some(Vec2::new(number("scaleW")?, number("scaleH")?));
+                    if number("pages")? > 1. {
+                        bail!("BMFont descriptors with more than one page are not supported.");
+                    }
+                }
+                "page" => {
+                    image = attributes.get("file").cloned();
+                }
+                "char" => {
+                    let id = number("id")? as u32;
+                    chars.push(char::from_u32(id)
+                        .ok_or_else(|| anyhow!("Invalid BMFont char id {}.", id))?);
+
+                    let min = Vec2::new(number("x")?, number("y")?);
+                    let size = Vec2::new(number("width")?, number("height")?);
+                    rects.push(Rect { min, max: min + size });
+
+                    metrics.push(GlyphMetrics {
+                        left: 0.,
+                        width: size.x,
+                        offset: Vec2::new(number("xoffset")?, number("yoffset")?),
+                        advance: number("xadvance")?,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        let line_height = line_height.ok_or_else(|| anyhow!("BMFont common line not found."))?;
+        let max_advance = metrics.iter().map(|m| m.advance).fold(0., f32::max);
+
+        let descriptor = FontDescriptor {
+            name: name.ok_or_else(|| anyhow!("BMFont info line not found."))?,
+            image: image.ok_or_else(|| anyhow!("BMFont page line not found."))?,
+            tile_size: Vec2::new(max_advance, line_height),
+            atlas: AtlasLayout::Rects(rects),
+            char_map: CharMap::new(chars.into_iter().enumerate(), None),
+            glyph_widths: GlyphWidths::Metrics(metrics),
+            letter_spacing: 0.,
+            line_height,
+            fallback_char: None,
+            fallback_fonts: vec![],
+        };
+        // The common line was found above, so the atlas size was too.
+        descriptor.check_atlas_size(atlas_size.unwrap_or_default())?;
+        Ok(descriptor)
+    }
+
+    // Fail if any glyph's rect reaches outside an atlas image of this size,
+    // as cutting the glyph out of the image would then panic.
+    pub fn check_atlas_size(&self, size: Vec2) -> Result<(), anyhow::Error> {
+        let rects = match &self.atlas {
+            AtlasLayout::Grid(grid_size) => vec![Rect {
+                min: Vec2::ZERO,
+                max: *grid_size * self.tile_size,
+            }],
+            AtlasLayout::Rects(rects) => rects.clone(),
+        };
+
+        for rect in rects.iter() {
+            if rect.min.x < 0. || rect.min.y < 0. || rect.max.x < rect.min.x
+                || rect.max.y < rect.min.y || rect.max.x > size.x || rect.max.y > size.y {
+                bail!("Font {} has a glyph at {:?} to {:?}, outside its {} by {} atlas.",
+                    self.name, rect.min, rect.max, size.x, size.y);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Split a BMFont line into its tag and its key=value attributes, unquoting any quoted values.
+fn parse_bmfont_line(line: &str) -> (String, HashMap<String, String>) {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    let mut tokens = tokens.into_iter();
+    let tag = tokens.next().unwrap_or_default();
+    let attributes = tokens
+        .filter_map(|t| t.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())))
+        .collect();
+
+    (tag, attributes)
+}
+
+// A loaded font descriptor, with a handle to its image.
+#[derive(TypeUuid)]
+#[uuid = "5a2c3f7e-91d4-4b8a-a6e2-0c7d4f1b9e36"]
+pub struct FontAsset {
+    pub descriptor: FontDescriptor,
+    pub image: Handle<Image>,
+}
+
+#[derive(Default)]
+pub struct FontDescriptorLoader;
+
+impl AssetLoader for FontDescriptorLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let descriptor = match load_context.path().extension().and_then(|e| e.to_str()) {
+                Some("fnt") => FontDescriptor::from_bmfont(std::str::from_utf8(bytes)?)?,
+                _ => FontDescriptor::from_json(bytes)?,
+            };
+
+            let directory = load_context.path().parent().unwrap_or_else(|| Path::new(""));
+            let image_path = AssetPath::new(directory.join(&descriptor.image), None);
+            let image = load_context.get_handle(image_path.clone());
+
+            load_context.set_default_asset(
+                LoadedAsset::new(FontAsset { descriptor, image }).with_dependency(image_path));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["font.json", "fnt"]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&str]) -> CharMapSpec {
+        CharMapSpec::Rows(rows.iter().map(|row| row.to_string()).collect())
+    }
+
+    #[test]
+    fn maps_rows_to_grid_positions() {
+        let char_map = CharMap::from_spec(rows(&["AB", "CDE"]), 3, 6, None).unwrap();
+        assert_eq!(char_map.index('A'), Some(0));
+        assert_eq!(char_map.index('B'), Some(1));
+        assert_eq!(char_map.index('C'), Some(3));
+        assert_eq!(char_map.index('E'), Some(5));
+        assert_eq!(char_map.chars(), [('A', 0), ('B', 1), ('C', 3), ('D', 4), ('E', 5)]);
+    }
+
+    #[test]
+    fn maps_rows_of_multibyte_characters() {
+        let char_map = CharMap::from_spec(rows(&["ÄÖ", "¿"]), 2, 4, None).unwrap();
+        assert_eq!(char_map.index('Ö'), Some(1));
+        assert_eq!(char_map.index('¿'), Some(2));
+    }
+
+    #[test]
+    fn rejects_rows_longer_than_the_grid() {
+        assert!(CharMap::from_spec(rows(&["ABCD"]), 3, 6, None).is_err());
+    }
+
+    #[test]
+    fn rejects_rows_past_the_last_glyph() {
+        assert!(CharMap::from_spec(rows(&["AB", "CD", "E"]), 2, 4, None).is_err());
+    }
+
+    #[test]
+    fn maps_codepoints_in_order() {
+        let spec = CharMapSpec::Codepoints(vec![65, 0xC9, 0x24B6]);
+        let char_map = CharMap::from_spec(spec, 3, 3, None).unwrap();
+        assert_eq!(char_map.index('A'), Some(0));
+        assert_eq!(char_map.index('É'), Some(1));
+        assert_eq!(char_map.index('Ⓐ'), Some(2));
+    }
+
+    #[test]
+    fn rejects_invalid_codepoints() {
+        assert!(CharMap::from_spec(CharMapSpec::Codepoints(vec![65, 0xD800]), 2, 2, None)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_codepoints_past_the_last_glyph() {
+        assert!(CharMap::from_spec(CharMapSpec::Codepoints(vec![65, 66, 67]), 2, 2, None)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_ascii_for_a_short_atlas() {
+        assert!(CharMap::from_spec(CharMapSpec::Ascii, 16, 59, None).is_err());
+        assert!(CharMap::from_spec(CharMapSpec::Ascii, 16, 96, None).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_first_of_repeated_characters() {
+        let char_map = CharMap::from_spec(CharMapSpec::Chars("ABA".into()), 3, 3, None).unwrap();
+        assert_eq!(char_map.index('A'), Some(0));
+    }
+
+    #[test]
+    fn folds_case() {
+        let char_map = CharMap::from_spec(rows(&["AÉS"]), 3, 3, Some(CaseFold::Upper)).unwrap();
+        assert_eq!(char_map.index('a'), Some(0));
+        assert_eq!(char_map.index('é'), Some(1));
+        // ß folds to two characters, so it isn't found as S.
+        assert_eq!(char_map.index('ß'), None);
+
+        let unfolded = CharMap::from_spec(rows(&["A"]), 1, 1, None).unwrap();
+        assert_eq!(unfolded.index('a'), None);
+    }
+
+    const BMFONT: &str = "\
+info face=\"Test Font\" size=8 bold=0
+common lineHeight=10 base=8 scaleW=16 scaleH=8 pages=1
+page id=0 file=\"test font.png\"
+chars count=2
+char id=65 x=0 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0
+char id=201 x=8 y=1 width=5 height=7 xoffset=1 yoffset=0 xadvance=7 page=0
+";
+
+    #[test]
+    fn reads_bmfont_descriptors() {
+        let font = FontDescriptor::from_bmfont(BMFONT).unwrap();
+        assert_eq!(font.name, "Test Font");
+        assert_eq!(font.image, "test font.png");
+        assert_eq!(font.line_height, 10.);
+        // As wide as the widest advance, so fallbacks and missing glyphs get a whole tile.
+        assert_eq!(font.tile_size, Vec2::new(7., 10.));
+        assert_eq!(font.char_map.index('A'), Some(0));
+        assert_eq!(font.char_map.index('É'), Some(1));
+
+        match &font.atlas {
+            AtlasLayout::Rects(rects) => {
+                assert_eq!(rects.len(), 2);
+                assert_eq!(rects[1].min, Vec2::new(8., 1.));
+                assert_eq!(rects[1].max, Vec2::new(13., 8.));
+            }
+            AtlasLayout::Grid(_) => panic!("BMFont atlas should be rects."),
+        }
+        match &font.glyph_widths {
+            GlyphWidths::Metrics(metrics) => {
+                assert_eq!(metrics[1].width, 5.);
+                assert_eq!(metrics[1].offset, Vec2::new(1., 0.));
+                assert_eq!(metrics[1].advance, 7.);
+            }
+            _ => panic!("BMFont glyph widths should be metrics."),
+        }
+    }
+
+    #[test]
+    fn rejects_bmfont_rects_outside_the_atlas() {
+        let past_right = BMFONT.replace("x=8 y=1 width=5", "x=12 y=1 width=5");
+        assert!(FontDescriptor::from_bmfont(&past_right).is_err());
+        let past_bottom = BMFONT.replace("x=8 y=1 width=5 height=7", "x=8 y=2 width=5 height=7");
+        assert!(FontDescriptor::from_bmfont(&past_bottom).is_err());
+        let negative = BMFONT.replace("x=0 y=0", "x=-1 y=0");
+        assert!(FontDescriptor::from_bmfont(&negative).is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_bmfont_descriptors() {
+        let multi_page = BMFONT.replace("pages=1", "pages=2");
+        assert!(FontDescriptor::from_bmfont(&multi_page).is_err());
+        let no_size = BMFONT.replace(" scaleW=16", "");
+        assert!(FontDescriptor::from_bmfont(&no_size).is_err());
+        let bad_number = BMFONT.replace("xadvance=6", "xadvance=six");
+        assert!(FontDescriptor::from_bmfont(&bad_number).is_err());
+        let no_page = BMFONT.lines()
+            .filter(|line| !line.starts_with("page"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(FontDescriptor::from_bmfont(&no_page).is_err());
+    }
+
+    #[test]
+    fn checks_grids_against_the_atlas() {
+        let font = FontDescriptor::from_json(br#"{
+            "name": "Grid",
+            "image": "grid.png",
+            "tile_size": [8, 8],
+            "grid_size": [4, 2],
+            "char_map": {"chars": "AB"}
+        }"#).unwrap();
+        assert!(font.check_atlas_size(Vec2::new(32., 16.)).is_ok());
+        assert!(font.check_atlas_size(Vec2::new(32., 8.)).is_err());
+    }
+
+    #[test]
+    fn splits_bmfont_lines_outside_quotes() {
+        let (tag, attributes) = parse_bmfont_line("info face=\"A B\"  size=8");
+        assert_eq!(tag, "info");
+        assert_eq!(attributes["face"], "A B");
+        assert_eq!(attributes["size"], "8");
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::{
+    prelude::*,
+    sprite::Anchor,
+};
+
+use super::display::Display;
+use super::font::{BitmapText, TextAlign};
+use super::markup::{color_tag, icon_tag};
+use super::panel::PanelAssets;
+use super::scene::{HeldTopping, Orders, Placement, Score, Topping};
+use super::state::{GameState, Shift, ShiftEntity};
+
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(SystemSet::on_enter(GameState::Shift)
+                .with_system(spawn_hud))
+            .add_system_set(SystemSet::on_update(GameState::Shift)
+                .with_system(update_hud));
+    }
+}
+
+const HUD_FONT: &str = "Dinobyte";
+const HUD_Z: f32 = 10.;
+const HUD_MARGIN: f32 = 4.;
+const HUD_PADDING: f32 = 2.;
+const HUD_ROW_HEIGHT: f32 = 12.;
+const ORDER_ROWS: usize = 3;
+
+#[derive(Component)]
+enum HudField {
+    Score,
+    Time,
+    Order(usize),
+    Held,
+}
+impl HudField {
+    fn box_size(&self) -> Vec2 {
+        match self {
+            HudField::Score => Vec2::new(100., HUD_ROW_HEIGHT),
+            HudField::Time => Vec2::new(64., HUD_ROW_HEIGHT),
+            HudField::Order(_) => Vec2::new(172., HUD_ROW_HEIGHT),
+            HudField::Held => Vec2::new(172., HUD_ROW_HEIGHT),
+        }
+    }
+
+    // The time sits in the top right corner; everything else stacks down the left edge.
+    fn anchor(&self) -> Anchor {
+        match self {
+            HudField::Time => Anchor::TopRight,
+            _ => Anchor::TopLeft,
+        }
+    }
+
+    fn align(&self) -> TextAlign {
+        match self {
+            HudField::Time => TextAlign::Right,
+            _ => TextAlign::Left,
+        }
+    }
+
+    // The position of the field's anchor, at the screen edge it's aligned to.
+    fn position(&self, display: &Display) -> Vec3 {
+        let row = match self {
+            HudField::Score | HudField::Time => 0,
+            HudField::Order(index) => index + 1,
+            HudField::Held => ORDER_ROWS + 1,
+        };
+        let x = match self {
+            HudField::Time => display.width / 2. - HUD_MARGIN,
+            _ => -display.width / 2. + HUD_MARGIN,
+        };
+        let y = display.height / 2. - HUD_MARGIN - row as f32 * HUD_ROW_HEIGHT;
+
+        Vec3::new(display.camera_x + x, display.camera_y + y, HUD_Z)
+    }
+
+    fn value(&self, score: &Score, shift: &Shift, orders: &Orders, held: &HeldTopping) -> String {
+        match self {
+            HudField::Score => format!("SCORE {}", score.0),
+            HudField::Time => {
+                let remaining = (shift.timer.duration() - shift.timer.elapsed())
+                    .as_secs_f32().ceil() as u32;
+                format!("TIME {}:{:02}", remaining / 60, remaining % 60)
+            }
+            HudField::Order(index) => match orders.0.get(*index) {
+                Some(order) => {
+                    // Every order has all the covering toppings, so only list the others.
+                    let extras: Vec<String> = order.toppings.iter()
+                        .filter(|t| matches!(t.placement, Placement::Scatter))
+                        .map(topping_label)
+                        .collect();
+                    format!("{} {}", index + 1,
+                        if extras.is_empty() { "PLAIN".into() } else { extras.join(" + ") })
+                }
+                None => "".into(),
+            },
+            HudField::Held => match &held.0 {
+                Some(topping) => format!("HOLDING {}", topping_label(topping)),
+                None => "".into(),
+            },
+        }
+    }
+}
+
+// A topping's icon and its name in its own colour.
+fn topping_label(topping: &Topping) -> String {
+    format!("{}{}", icon_tag(&topping.icon_name()),
+        color_tag(topping.color, &topping.name.to_uppercase()))
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    display: Res<Display>,
+    panels: Res<PanelAssets>,
+) {
+    let mut fields = vec![HudField::Score, HudField::Time, HudField::Held];
+    fields.extend((0..ORDER_ROWS).map(HudField::Order));
+
+    for field in fields {
+        commands.spawn()
+            .insert(BitmapText {
+                font: HUD_FONT.into(),
+                position: field.position(&display),
+                anchor: field.anchor(),
+                box_size: field.box_size(),
+                padding: HUD_PADDING,
+                align: field.align(),
+                background_color: match field {
+                    HudField::Order(_) => Color::NONE,
+                    _ => Color::rgba(0., 0., 0., 0.5),
+                },
+                // Show each order on a ticket.
+                background: match field {
+                    HudField::Order(_) => Some(panels.panel()),
+                    _ => None,
+                },
+                ..Default::default()
+            })
+            .insert(field)
+            .insert(ShiftEntity);
+    }
+}
+
+// Write each field's text only when its value changes, as every change re-renders the text.
+fn update_hud(
+    score: Res<Score>,
+    shift: Res<Shift>,
+    orders: Res<Orders>,
+    held: Res<HeldTopping>,
+    display: Res<Display>,
+    mut fields: Query<(&HudField, &mut BitmapText)>,
+) {
+    for (field, mut text) in fields.iter_mut() {
+        let value = field.value(&score, &shift, &orders, &held);
+        if text.text != value {
+            text.text = value;
+        }
+
+        let position = field.position(&display);
+        if text.position != position {
+            text.position = position;
+        }
+    }
+}
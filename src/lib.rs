@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+pub mod bounce;
+pub mod clock;
+pub mod constants;
+pub mod crt;
+pub mod cursor;
+pub mod cycle;
+pub mod display;
+pub mod effect;
+pub mod font;
+pub mod font_descriptor;
+pub mod hud;
+pub mod markup;
+pub mod menu;
+pub mod panel;
+pub mod scene;
+pub mod state;
+pub mod texture;
+
+
+#[derive(Component)]
+pub struct MainCamera;
@@ -2,19 +2,9 @@ use bevy::prelude::*;
 use bevy::render::texture::ImageSettings;
 use bevy::window::{WindowMode, WindowResizeConstraints};
 
-mod bounce;
-mod constants;
-mod cursor;
-mod cycle;
-mod display;
-mod font;
-mod scene;
+use pizzarust::*;
+use pizzarust::constants::*;
 
-use constants::*;
-
-
-#[derive(Component)]
-pub struct MainCamera;
 
 fn main() {
     App::new()
@@ -34,14 +24,20 @@ fn main() {
         })
         .insert_resource(ImageSettings::default_nearest())
         .add_startup_system_to_stage(StartupStage::PreStartup, init_cameras)
-        .add_system(bevy::window::close_on_esc)
         .add_plugins(DefaultPlugins)
         .add_plugin(bounce::BouncePlugin)
+        .add_plugin(clock::ClockPlugin)
+        .add_plugin(crt::CrtPlugin)
         .add_plugin(cursor::CursorPlugin)
         .add_plugin(cycle::CyclePlugin)
         .add_plugin(display::DisplayPlugin)
+        .add_plugin(effect::EffectPlugin)
         .add_plugin(font::FontPlugin)
+        .add_plugin(hud::HudPlugin)
+        .add_plugin(menu::MenuPlugin)
+        .add_plugin(panel::PanelPlugin)
         .add_plugin(scene::ScenePlugin)
+        .add_plugin(state::StatePlugin)
         .run();
 }
 
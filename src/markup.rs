@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+
+
+// Icons are replaced with characters from Unicode's private use area,
+// so they can be wrapped and measured along with the rest of the text.
+const ICON_CHAR_START: u32 = 0xE000;
+const ICON_CHAR_END: u32 = 0xF8FF;
+
+// Text with its markup tags taken out, the colour given to each character by any color tags,
+// and the names of the icons that its icon characters stand for.
+//
+// Markup looks like "[color=#f00]Red[/color]" and "[icon=pepperoni]".
+// Tags can be nested, "[[" is a literal "[", and anything else in brackets is left as it is.
+#[derive(Clone, Debug, Default)]
+pub struct RichText {
+    pub text: String,
+    pub colors: Vec<Option<Color>>,
+    pub icons: Vec<String>,
+}
+impl RichText {
+    pub fn parse(markup: &str) -> Self {
+        let mut rich = RichText::default();
+        let mut color_stack: Vec<Color> = vec![];
+
+        let mut rest = markup;
+        while let Some(c) = rest.chars().next() {
+            if let Some(tail) = rest.strip_prefix("[[") {
+                rich.push('[', color_stack.last().copied());
+                rest = tail;
+                continue;
+            }
+
+            if c == '[' {
+                if let Some(end) = rest.find(']') {
+                    let tag = &rest[1..end];
+                    let handled = if let Some(value) = tag.strip_prefix("color=") {
+                        parse_color(value).map(|color| color_stack.push(color)).is_some()
+                    } else if tag == "/color" {
+                        color_stack.pop();
+                        true
+                    } else if let Some(name) = tag.strip_prefix("icon=") {
+                        match char::from_u32(ICON_CHAR_START + rich.icons.len() as u32) {
+                            Some(icon_char) if is_icon_char(icon_char) => {
+                                rich.push(icon_char, color_stack.last().copied());
+                                rich.icons.push(name.into());
+                                true
+                            }
+                            _ => false,
+                        }
+                    } else {
+                        false
+                    };
+
+                    if handled {
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+
+            rich.push(c, color_stack.last().copied());
+            rest = &rest[c.len_utf8()..];
+        }
+
+        rich
+    }
+
+    fn push(&mut self, c: char, color: Option<Color>) {
+        self.text.push(c);
+        self.colors.push(color);
+    }
+
+    // The name of the icon that a character stands for, if it is an icon character.
+    pub fn icon(&self, c: char) -> Option<&str> {
+        if is_icon_char(c) {
+            self.icons.get((c as u32 - ICON_CHAR_START) as usize).map(|name| name.as_str())
+        } else {
+            None
+        }
+    }
+}
+
+pub fn is_icon_char(c: char) -> bool {
+    (ICON_CHAR_START..=ICON_CHAR_END).contains(&(c as u32))
+}
+
+// Parse "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa".
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+        _ => hex.to_string(),
+    };
+    Color::hex(hex).ok()
+}
+
+// Wrap text in a color tag.
+pub fn color_tag(color: Color, text: &str) -> String {
+    let [r, g, b, _] = color.as_rgba_f32();
+    format!("[color=#{:02x}{:02x}{:02x}]{}[/color]",
+        (r * 255.).round() as u8, (g * 255.).round() as u8, (b * 255.).round() as u8, text)
+}
+
+pub fn icon_tag(name: &str) -> String {
+    format!("[icon={}]", name)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Option<Color> = Some(Color::rgb(1., 0., 0.));
+    const GREEN: Option<Color> = Some(Color::rgb(0., 1., 0.));
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        let rich = RichText::parse("Plain text");
+        assert_eq!(rich.text, "Plain text");
+        assert_eq!(rich.colors, vec![None; 10]);
+    }
+
+    #[test]
+    fn colors_tagged_text() {
+        let rich = RichText::parse("a[color=#f00]bc[/color]d");
+        assert_eq!(rich.text, "abcd");
+        assert_eq!(rich.colors, [None, RED, RED, None]);
+    }
+
+    #[test]
+    fn reads_long_colors() {
+        let rich = RichText::parse("[color=#00ff00ff]a");
+        assert_eq!(rich.colors, [GREEN]);
+    }
+
+    #[test]
+    fn nests_color_tags() {
+        let rich = RichText::parse("[color=#f00]a[color=#0f0]b[/color]c[/color]d");
+        assert_eq!(rich.text, "abcd");
+        assert_eq!(rich.colors, [RED, GREEN, RED, None]);
+    }
+
+    #[test]
+    fn colors_to_the_end_when_unclosed() {
+        let rich = RichText::parse("a[color=#f00]bc");
+        assert_eq!(rich.text, "abc");
+        assert_eq!(rich.colors, [None, RED, RED]);
+    }
+
+    #[test]
+    fn ignores_unmatched_closing_tags() {
+        let rich = RichText::parse("a[/color]b");
+        assert_eq!(rich.text, "ab");
+        assert_eq!(rich.colors, [None, None]);
+    }
+
+    #[test]
+    fn leaves_unfinished_tags_as_text() {
+        assert_eq!(RichText::parse("a [color=#f00 b").text, "a [color=#f00 b");
+    }
+
+    #[test]
+    fn leaves_unknown_tags_and_bad_colors_as_text() {
+        assert_eq!(RichText::parse("[b]bold[/b]").text, "[b]bold[/b]");
+        assert_eq!(RichText::parse("[color=red]a").text, "[color=red]a");
+        assert_eq!(RichText::parse("[color=#ggg]a").text, "[color=#ggg]a");
+    }
+
+    #[test]
+    fn escapes_brackets() {
+        let rich = RichText::parse("[[color=#f00]a");
+        assert_eq!(rich.text, "[color=#f00]a");
+        assert!(rich.colors.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn replaces_icons_with_characters() {
+        let rich = RichText::parse("a[icon=pepperoni][color=#f00][icon=olive]");
+        let chars: Vec<char> = rich.text.chars().collect();
+        assert_eq!(chars.len(), 3);
+        assert_eq!(rich.icon(chars[0]), None);
+        assert_eq!(rich.icon(chars[1]), Some("pepperoni"));
+        assert_eq!(rich.icon(chars[2]), Some("olive"));
+        assert_eq!(rich.colors, [None, None, RED]);
+    }
+
+    #[test]
+    fn parses_its_own_tags() {
+        let markup = format!("{}{}", color_tag(Color::rgb(1., 0., 0.), "hot"), icon_tag("chili"));
+        let rich = RichText::parse(&markup);
+        assert_eq!(rich.text.chars().count(), 4);
+        assert!(rich.text.starts_with("hot"));
+        assert_eq!(rich.colors[..3], [RED, RED, RED]);
+        assert_eq!(rich.icons, ["chili"]);
+    }
+}
@@ -0,0 +1,362 @@
+use bevy::{
+    app::AppExit,
+    input::{
+        ButtonState,
+        mouse::{MouseButton, MouseButtonInput},
+    },
+    math::Vec3Swizzles,
+    prelude::*,
+    window::CursorMoved,
+};
+
+use super::MainCamera;
+use super::bounce::{EffectType, GlyphEffect, GlyphEffectType, GlyphEffects};
+use super::crt::ToggleCrtEvent;
+use super::cursor;
+use super::cycle::{CycleMaterial, CycleTime};
+use super::display::{Display, FollowCamera, SwitchResolutionEvent};
+use super::font::{BitmapText, TextRenderMode};
+use super::panel::{Panel, PanelAssets};
+use super::scene::Score;
+use super::state::{GameState, PauseEntity, Shift, SummaryEntity, TitleEntity};
+
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ActiveMenu::default())
+            .add_event::<MenuEvent>()
+            .add_system_set(SystemSet::on_enter(GameState::Title)
+                .with_system(title_screen))
+            .add_system_set(SystemSet::on_enter(GameState::ShiftSummary)
+                .with_system(summary_screen))
+            .add_system_set(SystemSet::on_enter(GameState::Paused)
+                .with_system(pause_menu))
+            .add_system(navigate_menu)
+            .add_system(run_menu_actions.after(navigate_menu))
+            .add_system(highlight_menu_items.after(navigate_menu));
+    }
+}
+
+const MENU_FONT: &str = "GeeBee";
+const MENU_Z: f32 = 10.;
+const MENU_ITEM_SPACING: f32 = 14.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuAction {
+    Start,
+    Options,
+    Quit,
+    Resolution,
+    Crt,
+    Back,
+    Resume,
+    NextShift,
+    QuitToTitle,
+}
+impl MenuAction {
+    fn label(&self) -> &str {
+        match self {
+            MenuAction::Start => "START",
+            MenuAction::Options => "OPTIONS",
+            MenuAction::Quit => "QUIT",
+            MenuAction::Resolution => "RESOLUTION",
+            MenuAction::Crt => "CRT FILTER",
+            MenuAction::Back => "BACK",
+            MenuAction::Resume => "RESUME",
+            MenuAction::NextShift => "NEXT SHIFT",
+            MenuAction::QuitToTitle => "QUIT TO TITLE",
+        }
+    }
+}
+
+#[derive(Component)]
+struct MenuItem {
+    action: MenuAction,
+    index: usize,
+}
+
+// The menu currently on screen: which item is selected, and what Escape does.
+#[derive(Default)]
+struct ActiveMenu {
+    selected: usize,
+    cancel: Option<MenuAction>,
+}
+
+struct MenuEvent(MenuAction);
+
+fn spawn_menu<T: Component + Clone>(
+    commands: &mut Commands,
+    menu: &mut ActiveMenu,
+    display: &Display,
+    offset_y: f32,
+    actions: &[MenuAction],
+    cancel: Option<MenuAction>,
+    marker: T,
+) {
+    for (index, action) in actions.iter().enumerate() {
+        commands.spawn()
+            .insert(BitmapText {
+                text: action.label().into(),
+                font: MENU_FONT.into(),
+                padding: 2.,
+                // Keep the items readable over the background grid.
+                outline: Some(Color::BLACK),
+                // Highlights keep cycling while the game is paused under the menu.
+                cycle_time: CycleTime {
+                    real_time: true,
+                    ..Default::default()
+                },
+                position: Vec3::new(
+                    display.camera_x,
+                    display.camera_y + offset_y - index as f32 * MENU_ITEM_SPACING,
+                    MENU_Z),
+                ..Default::default()
+            })
+            .insert(MenuItem {
+                action: *action,
+                index,
+            })
+            .insert(FollowCamera)
+            .insert(marker.clone());
+    }
+
+    *menu = ActiveMenu {
+        selected: 0,
+        cancel,
+    };
+}
+
+fn spawn_title_menu(
+    commands: &mut Commands,
+    menu: &mut ActiveMenu,
+    display: &Display,
+) {
+    spawn_menu(commands, menu, display, -10.,
+        &[MenuAction::Start, MenuAction::Options, MenuAction::Quit],
+        Some(MenuAction::Quit), TitleEntity);
+}
+
+fn spawn_options_menu(
+    commands: &mut Commands,
+    menu: &mut ActiveMenu,
+    display: &Display,
+) {
+    spawn_menu(commands, menu, display, -10.,
+        &[MenuAction::Resolution, MenuAction::Crt, MenuAction::Back],
+        Some(MenuAction::Back), TitleEntity);
+}
+
+fn title_screen(
+    mut commands: Commands,
+    mut menu: ResMut<ActiveMenu>,
+    display: Res<Display>,
+) {
+    commands.spawn()
+        .insert(BitmapText {
+            text: "PIZZA!".into(),
+            font: "Elecfont".into(),
+            padding: 6.,
+            position: Vec3::new(display.camera_x, display.camera_y + 40., MENU_Z),
+            render_mode: TextRenderMode::Glyphs,
+            ..Default::default()
+        })
+        .insert(GlyphEffects {
+            effects: vec![
+                GlyphEffect {
+                    effect_type: GlyphEffectType::Wave(EffectType::Bounce),
+                    distance: 10.,
+                    period: 0.75,
+                    phase_step: 0.1,
+                },
+                GlyphEffect {
+                    effect_type: GlyphEffectType::ColorCycle,
+                    period: 2.,
+                    phase_step: 0.15,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        })
+        .insert(FollowCamera)
+        .insert(TitleEntity);
+
+    spawn_title_menu(&mut commands, &mut menu, &display);
+}
+
+fn summary_screen(
+    mut commands: Commands,
+    mut menu: ResMut<ActiveMenu>,
+    shift: Res<Shift>,
+    score: Res<Score>,
+    display: Res<Display>,
+) {
+    commands.spawn()
+        .insert(BitmapText {
+            text: format!("SHIFT {} OVER - SCORE {}", shift.number, score.0),
+            font: MENU_FONT.into(),
+            padding: 4.,
+            background_color: Color::rgb(0.1, 0.1, 0.2),
+            position: Vec3::new(display.camera_x, display.camera_y + 20., MENU_Z),
+            ..Default::default()
+        })
+        .insert(FollowCamera)
+        .insert(SummaryEntity);
+
+    spawn_menu(&mut commands, &mut menu, &display, -10.,
+        &[MenuAction::NextShift, MenuAction::QuitToTitle],
+        None, SummaryEntity);
+}
+
+fn pause_menu(
+    mut commands: Commands,
+    mut menu: ResMut<ActiveMenu>,
+    display: Res<Display>,
+    panels: Res<PanelAssets>,
+) {
+    commands.spawn()
+        .insert(Panel {
+            nine_slice: panels.panel(),
+            position: Vec3::new(display.camera_x, display.camera_y + 8., MENU_Z - 1.),
+            size: Vec2::new(160., 92.),
+        })
+        .insert(FollowCamera)
+        .insert(PauseEntity);
+
+    commands.spawn()
+        .insert(BitmapText {
+            text: "PAUSED".into(),
+            font: "Elecfont".into(),
+            padding: 6.,
+            background_color: Color::rgba(0., 0., 0., 0.5),
+            position: Vec3::new(display.camera_x, display.camera_y + 30., MENU_Z),
+            ..Default::default()
+        })
+        .insert(FollowCamera)
+        .insert(PauseEntity);
+
+    spawn_menu(&mut commands, &mut menu, &display, -10.,
+        &[MenuAction::Resume, MenuAction::QuitToTitle],
+        Some(MenuAction::Resume), PauseEntity);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn navigate_menu(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut click_events: EventReader<MouseButtonInput>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    mut menu_events: EventWriter<MenuEvent>,
+    mut menu: ResMut<ActiveMenu>,
+    windows: Res<Windows>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    items: Query<(&MenuItem, &Transform)>,
+) {
+    let count = items.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + count - 1) % count;
+    } else if keys.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % count;
+    } else if keys.just_pressed(KeyCode::Return) {
+        keys.reset(KeyCode::Return);
+        if let Some((item, _)) = items.iter().find(|(i, _)| i.index == menu.selected) {
+            menu_events.send(MenuEvent(item.action));
+        }
+    } else if keys.just_pressed(KeyCode::Escape) {
+        keys.reset(KeyCode::Escape);
+        if let Some(action) = menu.cancel {
+            menu_events.send(MenuEvent(action));
+        }
+    }
+
+    // Select the item under the cursor when it moves, and activate it when clicked.
+    let moved = cursor_moved_events.iter().count() > 0;
+    let clicked = click_events.iter().any(|e| {
+        e.button == MouseButton::Left && e.state == ButtonState::Released
+    });
+    if moved || clicked {
+        if let Some(position) = cursor::get_world_coords(windows, cameras) {
+            if let Some((item, _)) = items.iter().find(|(_, t)| {
+                let diff = (t.translation.xy() - position).abs();
+                diff.x < t.scale.x / 2. && diff.y < t.scale.y / 2.
+            }) {
+                if menu.selected != item.index {
+                    menu.selected = item.index;
+                }
+                if clicked {
+                    menu_events.send(MenuEvent(item.action));
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_menu_actions(
+    mut commands: Commands,
+    mut menu_events: EventReader<MenuEvent>,
+    mut menu: ResMut<ActiveMenu>,
+    mut state: ResMut<State<GameState>>,
+    mut exit_events: EventWriter<AppExit>,
+    mut resolution_events: EventWriter<SwitchResolutionEvent>,
+    mut crt_events: EventWriter<ToggleCrtEvent>,
+    display: Res<Display>,
+    items: Query<Entity, With<MenuItem>>,
+) {
+    // Only act on the last choice, so two clicks in one frame can't queue two state changes.
+    if let Some(MenuEvent(action)) = menu_events.iter().last() {
+        match action {
+            MenuAction::Start | MenuAction::NextShift => {
+                state.set(GameState::Shift).expect("Failed to start shift.");
+            }
+            MenuAction::Options | MenuAction::Back => {
+                for entity in items.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                if *action == MenuAction::Options {
+                    spawn_options_menu(&mut commands, &mut menu, &display);
+                } else {
+                    spawn_title_menu(&mut commands, &mut menu, &display);
+                }
+            }
+            MenuAction::Quit => {
+                exit_events.send(AppExit);
+            }
+            MenuAction::Resolution => {
+                resolution_events.send(SwitchResolutionEvent);
+            }
+            MenuAction::Crt => {
+                crt_events.send(ToggleCrtEvent);
+            }
+            MenuAction::Resume => {
+                state.pop().expect("Failed to resume.");
+            }
+            MenuAction::QuitToTitle => {
+                // Replacing the whole stack also exits a paused shift underneath.
+                state.replace(GameState::Title).expect("Failed to return to title.");
+            }
+        }
+    }
+}
+
+fn highlight_menu_items(
+    menu: Res<ActiveMenu>,
+    mut materials: ResMut<Assets<CycleMaterial>>,
+    items: Query<(&MenuItem, &Handle<CycleMaterial>)>,
+) {
+    for (item, handle) in items.iter() {
+        let enabled = (item.index == menu.selected) as u32;
+
+        // Only touch materials that need to change, as getting one mutably re-uploads it.
+        if materials.get(handle).is_some_and(|m| m.enabled != enabled) {
+            if let Some(material) = materials.get_mut(handle) {
+                material.enabled = enabled;
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use image::RgbaImage;
+
+use super::texture::{from_texture, to_texture};
+
+
+pub struct PanelPlugin;
+
+impl Plugin for PanelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(render_panels);
+    }
+}
+
+// Border images for panels, loaded with the fonts.
+#[derive(AssetCollection)]
+pub struct PanelAssets {
+    #[asset(path = "panel.png")]
+    pub panel: Handle<Image>,
+}
+impl PanelAssets {
+    pub fn panel(&self) -> NineSlice {
+        NineSlice::uniform(self.panel.clone(), 3)
+    }
+}
+
+// A border image, cut into corners, edges and a centre by the width of each side of its border.
+// Corners are drawn as they are, and edges and the centre are tiled to fill the space between.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NineSlice {
+    pub image: Handle<Image>,
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+impl NineSlice {
+    pub fn uniform(image: Handle<Image>, border: u32) -> Self {
+        NineSlice {
+            image,
+            left: border,
+            right: border,
+            top: border,
+            bottom: border,
+        }
+    }
+
+    // Draw the panel at a size, or return None if its image hasn't loaded yet.
+    pub fn draw(&self, images: &Assets<Image>, width: u32, height: u32) -> Option<RgbaImage> {
+        let source = from_texture(images.get(&self.image)?)?;
+        Some(draw_nine_slice(&source, self, width, height))
+    }
+}
+
+// Which row or column of the source image to draw at a point along the panel.
+fn slice_coord(dest: u32, dest_len: u32, start: u32, end: u32, source_len: u32) -> u32 {
+    if dest < start {
+        dest
+    } else if dest >= dest_len.saturating_sub(end) {
+        source_len - (dest_len - dest)
+    } else {
+        let middle = source_len.saturating_sub(start + end);
+        if middle == 0 { start.min(source_len - 1) } else { start + (dest - start) % middle }
+    }
+}
+
+pub fn draw_nine_slice(
+    source: &RgbaImage,
+    slice: &NineSlice,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    let (source_width, source_height) = source.dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        *source.get_pixel(
+            slice_coord(x, width, slice.left, slice.right, source_width),
+            slice_coord(y, height, slice.top, slice.bottom, source_height),
+        )
+    })
+}
+
+// A panel drawn from a border image, for backgrounds behind groups of UI.
+#[derive(Component)]
+pub struct Panel {
+    pub nine_slice: NineSlice,
+    pub position: Vec3,
+    pub size: Vec2,
+}
+
+// Where a panel was last drawn, so a redraw only moves it by the change in its position,
+// keeping any offset added since, such as by FollowCamera.
+#[derive(Component)]
+struct RenderedPanel {
+    position: Vec3,
+}
+
+// Redraw panels when they change, at their exact size in pixels so the border stays crisp.
+#[allow(clippy::type_complexity)]
+fn render_panels(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut panels: Query<(
+        Entity,
+        &Panel,
+        Option<&Handle<Image>>,
+        Option<&mut RenderedPanel>,
+        Option<&mut Transform>,
+    ), Changed<Panel>>,
+) {
+    for (entity, panel, existing_image, rendered, transform) in panels.iter_mut() {
+        let (width, height) = (panel.size.x as u32, panel.size.y as u32);
+        if let Some(image) = panel.nine_slice.draw(&images, width, height) {
+            match (existing_image, rendered, transform) {
+                (Some(image_handle), Some(mut rendered), Some(mut transform)) => {
+                    // Already drawn: replace the existing image, so the old one isn't left behind.
+                    let texture = images.get_mut(image_handle).expect("Panel image not found.");
+                    *texture = to_texture(image);
+                    transform.translation += panel.position - rendered.position;
+                    rendered.position = panel.position;
+                }
+                _ => {
+                    let texture = images.add(to_texture(image));
+
+                    commands.entity(entity)
+                        .insert_bundle(SpriteBundle {
+                            texture,
+                            transform: Transform::from_translation(panel.position),
+                            ..Default::default()
+                        })
+                        .insert(RenderedPanel {
+                            position: panel.position,
+                        });
+                }
+            }
+        }
+    }
+}
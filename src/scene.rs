@@ -11,8 +11,11 @@ use rand::{thread_rng, Rng};
 use std::f32::consts::TAU;
 
 use super::MainCamera;
+use super::clock::GameClock;
 use super::cursor;
 use super::display::Display;
+use super::font::{TextIcon, TextIcons};
+use super::state::{GameState, ShiftEntity};
 
 
 pub struct ScenePlugin;
@@ -21,11 +24,16 @@ impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
         app
             .insert_resource(HeldTopping(None))
+            .insert_resource(Orders(vec![]))
+            .insert_resource(Score(0))
             .add_event::<ToppingClickEvent>()
             .add_startup_system(background)
-            .add_startup_system(scene)
-            .add_system(move_pizzas)
-            .add_system(click_toppings);
+            .add_startup_system(topping_icons)
+            .add_system_set(SystemSet::on_enter(GameState::Shift)
+                .with_system(scene))
+            .add_system_set(SystemSet::on_update(GameState::Shift)
+                .with_system(move_pizzas)
+                .with_system(click_toppings));
     }
 }
 
@@ -35,6 +43,8 @@ const CONVEYOR_SPEED: f32 = 20.;
 const PIZZA_Z: f32 = 2.;
 const PIZZA_SPAWN_MARGIN: f32 = 40.;
 const TOPPING_Z: f32 = 3.;
+const ORDER_COUNT: usize = 3;
+const POINTS_PER_TOPPING: u32 = 10;
 
 #[derive(Component)]
 struct Conveyor {
@@ -58,6 +68,12 @@ pub struct Topping {
     pub color: Color,
     pub placement: Placement,
 }
+impl Topping {
+    // The name of the topping's icon in text markup.
+    pub fn icon_name(&self) -> String {
+        self.name.to_lowercase().replace(' ', "-")
+    }
+}
 
 #[derive(Component, Debug)]
 struct Container {
@@ -65,12 +81,68 @@ struct Container {
 }
 
 #[derive(Component)]
-struct HeldTopping(Option<Topping>);
+pub struct HeldTopping(pub Option<Topping>);
+
+#[derive(Clone, Debug)]
+pub struct Order {
+    pub toppings: Vec<Topping>,
+}
+impl Order {
+    fn random(toppings: &[Topping]) -> Self {
+        let mut rng = thread_rng();
+
+        // Every pizza gets all the covering toppings, plus a random selection of the others.
+        Order {
+            toppings: toppings.iter()
+                .filter(|t| matches!(t.placement, Placement::Cover) || rng.gen_bool(0.5))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn is_filled_by(&self, pizza: &Pizza) -> bool {
+        let mut wanted: Vec<&str> = self.toppings.iter().map(|t| t.name.as_str()).collect();
+        let mut added: Vec<&str> = pizza.toppings.iter().map(|t| t.name.as_str()).collect();
+        wanted.sort();
+        added.sort();
+        added.dedup();
+        wanted == added
+    }
+}
+
+pub struct Orders(pub Vec<Order>);
+
+pub struct Score(pub u32);
 
 #[derive(Debug)]
 pub struct ToppingClickEvent(pub Option<Topping>);
 
 
+fn toppings() -> Vec<Topping> {
+    vec![
+        Topping {
+            name: "Tomato Sauce".into(),
+            color: Color::rgb(0.5, 0.2, 0.1),
+            placement: Placement::Cover,
+        },
+        Topping {
+            name: "Mozzarella".into(),
+            color: Color::rgb(0.9, 0.9, 0.7),
+            placement: Placement::Cover,
+        },
+        Topping {
+            name: "Pepperoni".into(),
+            color: Color::rgb(0.6, 0.2, 0.1),
+            placement: Placement::Scatter,
+        },
+        Topping {
+            name: "Green Peppers".into(),
+            color: Color::rgb(0.4, 0.5, 0.1),
+            placement: Placement::Scatter,
+        },
+    ]
+}
+
 fn background(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -81,9 +153,33 @@ fn background(
     });
 }
 
+// Register an icon for each topping, so text can show them with icon tags.
+fn topping_icons(
+    asset_server: Res<AssetServer>,
+    mut icons: ResMut<TextIcons>,
+) {
+    let image = asset_server.load("circle10.png");
+    for topping in toppings() {
+        icons.0.insert(topping.icon_name(), TextIcon {
+            image: image.clone(),
+            color: topping.color,
+        });
+    }
+}
+
 fn scene(
     mut commands: Commands,
+    mut topping_events: EventWriter<ToppingClickEvent>,
 ) {
+    // Start each shift empty-handed.
+    topping_events.send(ToppingClickEvent(None));
+    commands.insert_resource(HeldTopping(None));
+
+    let toppings = toppings();
+    commands.insert_resource(Score(0));
+    commands.insert_resource(Orders(
+        (0..ORDER_COUNT).map(|_| Order::random(&toppings)).collect()));
+
     // Conveyor belt
     commands
         .spawn_bundle(SpriteBundle {
@@ -100,30 +196,8 @@ fn scene(
         })
         .insert(Conveyor {
             timer: Timer::from_seconds(1. / CONVEYOR_SPEED, true),
-        });
-
-    let toppings = vec![
-        Topping {
-            name: "Tomato Sauce".into(),
-            color: Color::rgb(0.5, 0.2, 0.1),
-            placement: Placement::Cover,
-        },
-        Topping {
-            name: "Mozzarella".into(),
-            color: Color::rgb(0.9, 0.9, 0.7),
-            placement: Placement::Cover,
-        },
-        Topping {
-            name: "Pepperoni".into(),
-            color: Color::rgb(0.6, 0.2, 0.1),
-            placement: Placement::Scatter,
-        },
-        Topping {
-            name: "Green Peppers".into(),
-            color: Color::rgb(0.4, 0.5, 0.1),
-            placement: Placement::Scatter,
-        },
-    ];
+        })
+        .insert(ShiftEntity);
 
     for (x, topping) in toppings.iter().enumerate() {
         commands
@@ -141,36 +215,47 @@ fn scene(
             })
             .insert(Container {
                 topping: (*topping).clone(),
-            });
+            })
+            .insert(ShiftEntity);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn move_pizzas(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     display: Res<Display>,
-    time: Res<Time>,
-    mut pizzas: Query<(Entity, &mut Transform), With<Pizza>>,
+    clock: Res<GameClock>,
+    mut orders: ResMut<Orders>,
+    mut score: ResMut<Score>,
+    mut pizzas: Query<(Entity, &mut Transform, &Pizza)>,
     mut conveyors: Query<&mut Conveyor>,
 ) {
     let spawn_x = display.camera_x - display.width / 2. - PIZZA_SPAWN_MARGIN;
     let despawn_x = display.camera_x + display.width / 2. + PIZZA_SPAWN_MARGIN;
 
     if let Ok(mut conveyor) = conveyors.get_single_mut() {
-        if conveyor.timer.tick(time.delta()).just_finished() {
-            for (entity, mut transform) in pizzas.iter_mut() {
+        if conveyor.timer.tick(clock.delta()).just_finished() {
+            for (entity, mut transform, pizza) in pizzas.iter_mut() {
                 // Move pizzas.
                 transform.translation.x += conveyor.timer.times_finished_this_tick() as f32;
 
-                // Remove any pizzas that have moved past the right edge.
+                // Remove any pizzas that have moved past the right edge,
+                // and score them if they fill one of the orders.
                 if transform.translation.x >= despawn_x {
+                    if let Some(index) = orders.0.iter().position(|o| o.is_filled_by(pizza)) {
+                        let order = orders.0.remove(index);
+                        score.0 += order.toppings.len() as u32 * POINTS_PER_TOPPING;
+                        orders.0.push(Order::random(&toppings()));
+                    }
+
                     commands.entity(entity).despawn_recursive();
                 }
             }
 
             // If there is enough free space to the left, create a new pizza off screen.
             let min_clearance_x = spawn_x + 160.;
-            let leftmost_pizza_x = pizzas.iter().map(|(_e, t)| t.translation.x).reduce(f32::min);
+            let leftmost_pizza_x = pizzas.iter().map(|(_e, t, _p)| t.translation.x).reduce(f32::min);
             if leftmost_pizza_x.unwrap_or(f32::MAX) > min_clearance_x {
                 commands
                     .spawn_bundle(SpriteBundle {
@@ -179,7 +264,8 @@ fn move_pizzas(
                             Vec3::new(spawn_x, CONVEYOR_Y, PIZZA_Z)),
                         ..Default::default()
                     })
-                    .insert(Pizza::default());
+                    .insert(Pizza::default())
+                    .insert(ShiftEntity);
             }
         }
     }
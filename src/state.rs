@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use super::clock::GameClock;
+
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Shift {
+                number: 0,
+                timer: Timer::from_seconds(SHIFT_SECONDS, false),
+            })
+            .add_state(GameState::Boot)
+            .add_system_set(SystemSet::on_update(GameState::Boot)
+                .with_system(boot))
+            .add_system_set(SystemSet::on_exit(GameState::Title)
+                .with_system(despawn_with::<TitleEntity>))
+            .add_system_set(SystemSet::on_enter(GameState::Shift)
+                .with_system(start_shift))
+            .add_system_set(SystemSet::on_update(GameState::Shift)
+                .with_system(tick_shift)
+                .with_system(pause_on_esc))
+            .add_system_set(SystemSet::on_exit(GameState::Shift)
+                .with_system(despawn_with::<ShiftEntity>))
+            .add_system_set(SystemSet::on_exit(GameState::ShiftSummary)
+                .with_system(despawn_with::<SummaryEntity>))
+            .add_system_set(SystemSet::on_enter(GameState::Paused)
+                .with_system(pause_clock))
+            .add_system_set(SystemSet::on_exit(GameState::Paused)
+                .with_system(resume_clock)
+                .with_system(despawn_with::<PauseEntity>));
+    }
+}
+
+const SHIFT_SECONDS: f32 = 120.;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum GameState {
+    Boot,
+    Loading,
+    Title,
+    Shift,
+    ShiftSummary,
+    // Pushed on top of Shift, so the shift's entities and timers survive until it is popped.
+    Paused,
+}
+
+pub struct Shift {
+    pub number: u32,
+    pub timer: Timer,
+}
+
+// Markers for entities that are despawned when their state is exited.
+
+#[derive(Component, Clone)]
+pub struct TitleEntity;
+
+#[derive(Component, Clone)]
+pub struct ShiftEntity;
+
+#[derive(Component, Clone)]
+pub struct SummaryEntity;
+
+#[derive(Component, Clone)]
+pub struct PauseEntity;
+
+fn boot(
+    mut state: ResMut<State<GameState>>,
+) {
+    state.set(GameState::Loading).expect("Failed to start loading.");
+}
+
+fn start_shift(
+    mut shift: ResMut<Shift>,
+) {
+    shift.number += 1;
+    shift.timer.reset();
+}
+
+fn tick_shift(
+    mut shift: ResMut<Shift>,
+    mut state: ResMut<State<GameState>>,
+    clock: Res<GameClock>,
+) {
+    if shift.timer.tick(clock.delta()).just_finished() {
+        state.set(GameState::ShiftSummary).expect("Failed to end shift.");
+    }
+}
+
+fn pause_on_esc(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        // Reset the key so the pause menu doesn't see the same press and resume immediately.
+        keys.reset(KeyCode::Escape);
+        state.push(GameState::Paused).expect("Failed to pause.");
+    }
+}
+
+fn pause_clock(
+    mut clock: ResMut<GameClock>,
+) {
+    clock.paused = true;
+}
+
+fn resume_clock(
+    mut clock: ResMut<GameClock>,
+) {
+    clock.paused = false;
+}
+
+fn despawn_with<T: Component>(
+    mut commands: Commands,
+    entities: Query<Entity, With<T>>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
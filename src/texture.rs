@@ -0,0 +1,32 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use image::RgbaImage;
+
+
+// Copy an RGBA texture's pixels into an image that can be drawn on.
+// Returns None for textures in any other format, as their bytes aren't RGBA pixels.
+pub fn from_texture(texture: &Image) -> Option<RgbaImage> {
+    match texture.texture_descriptor.format {
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => RgbaImage::from_raw(
+            texture.texture_descriptor.size.width,
+            texture.texture_descriptor.size.height,
+            texture.data.clone(),
+        ),
+        _ => None,
+    }
+}
+
+pub fn to_texture(image: RgbaImage) -> Image {
+    Image::new(
+        Extent3d {
+            width: image.width(),
+            height: image.height(),
+            ..Default::default()
+        },
+        TextureDimension::D2,
+        image.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
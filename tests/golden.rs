@@ -0,0 +1,165 @@
+// Golden-image tests for text rendering: known strings are rendered in each bundled font
+// and compared pixel for pixel against the reference images in tests/golden.
+//
+// When a render doesn't match, the render and an image highlighting the differing pixels
+// are written next to each other in the target directory.
+// After an intended change, set UPDATE_GOLDEN=1 to overwrite the references with new renders.
+
+use bevy::prelude::*;
+use image::{Rgba, RgbaImage};
+use std::{env, fs, path::{Path, PathBuf}};
+
+use pizzarust::font::{render_text_image, BitmapFont, BitmapText, TextAlign, TextImages};
+
+
+const FONT_DESCRIPTORS: [&str; 6] = [
+    "bluepink.font.json",
+    "dinobyte.font.json",
+    "elecfont.font.json",
+    "fantfont.font.json",
+    "geebee.font.json",
+    "tsk.font.json",
+];
+
+const PANGRAM: &str = "The quick brown fox jumps over the lazy dog.";
+const DIGITS: &str = "0123456789 (1,234.50)";
+// Every accented letter and button glyph GeeBee has, including lowercase folded to upper.
+const ACCENTED: &str = "¿Año? Ça, über Ärger. Í Á Ú Ó É À Ì Ù „Ñ\" Press Ⓐ or Ⓑ, ⬆ ⬇";
+
+fn load_fonts() -> Vec<BitmapFont> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fonts");
+    FONT_DESCRIPTORS.iter()
+        .map(|descriptor| BitmapFont::from_file(&directory.join(descriptor))
+            .expect("Font not found."))
+        .collect()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+// Highlight pixels that differ in red, over a faded copy of the reference.
+fn diff_image(expected: &RgbaImage, actual: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let (e, a) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        if e == a {
+            let grey = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 6) as u8;
+            Rgba([grey, grey, grey, 255])
+        } else {
+            Rgba([255, 0, 0, 255])
+        }
+    })
+}
+
+fn assert_golden(name: &str, text: BitmapText, fonts: &[BitmapFont]) {
+    let actual = render_text_image(&text, fonts, &TextImages::default());
+    let path = golden_path(name);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).expect("Golden directory not created.");
+        actual.save(&path).expect("Golden image not saved.");
+        return;
+    }
+
+    let expected = match image::open(&path) {
+        Ok(expected) => expected.to_rgba8(),
+        Err(error) => panic!(
+            "Golden image {} not loaded ({}). Run with UPDATE_GOLDEN=1 to create it.",
+            path.display(), error),
+    };
+
+    let mismatched = if expected.dimensions() != actual.dimensions() {
+        Some(format!("size is {:?}, expected {:?}", actual.dimensions(), expected.dimensions()))
+    } else {
+        let count = expected.pixels().zip(actual.pixels()).filter(|(e, a)| e != a).count();
+        (count > 0).then(|| format!("{} pixels differ", count))
+    };
+
+    if let Some(reason) = mismatched {
+        let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&output).expect("Output directory not created.");
+
+        let actual_path = output.join(format!("{}.actual.png", name));
+        actual.save(&actual_path).expect("Render not saved.");
+        let mut written = vec![actual_path];
+        if expected.dimensions() == actual.dimensions() {
+            let diff_path = output.join(format!("{}.diff.png", name));
+            diff_image(&expected, &actual).save(&diff_path).expect("Diff image not saved.");
+            written.push(diff_path);
+        }
+
+        let written: Vec<String> = written.iter().map(|p| p.display().to_string()).collect();
+        panic!("Render of {} doesn't match its golden image: {}. Wrote {}.",
+            name, reason, written.join(" and "));
+    }
+}
+
+fn sample(font: &str, text: &str) -> BitmapText {
+    BitmapText {
+        text: text.into(),
+        font: font.into(),
+        box_size: Vec2::new(320., 0.),
+        padding: 4.,
+        // Dinobyte's glyphs are drawn in black, so they need a light background to be seen.
+        background_color: if font == "Dinobyte" { Color::WHITE } else { Color::BLACK },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bluepink() {
+    assert_golden("bluepink", sample("BluePink", PANGRAM), &load_fonts());
+}
+
+#[test]
+fn dinobyte() {
+    assert_golden("dinobyte", sample("Dinobyte", PANGRAM), &load_fonts());
+}
+
+#[test]
+fn elecfont() {
+    assert_golden("elecfont", sample("Elecfont", PANGRAM), &load_fonts());
+}
+
+#[test]
+fn fantfont() {
+    assert_golden("fantfont", sample("Fantfont", PANGRAM), &load_fonts());
+}
+
+#[test]
+fn geebee() {
+    assert_golden("geebee", sample("GeeBee", PANGRAM), &load_fonts());
+}
+
+#[test]
+fn geebee_accented() {
+    assert_golden("geebee-accented", sample("GeeBee", ACCENTED), &load_fonts());
+}
+
+// TSK has no lowercase or most punctuation, so this also covers case folding and fallbacks.
+#[test]
+fn tsk() {
+    assert_golden("tsk", sample("TSK", "Pizza time! Press [[Enter]"), &load_fonts());
+}
+
+#[test]
+fn digits() {
+    let fonts = load_fonts();
+    for font in fonts.iter() {
+        let name = font.info().name.clone();
+        let golden = format!("digits-{}", name.to_lowercase());
+        assert_golden(&golden, sample(&name, DIGITS), &fonts);
+    }
+}
+
+#[test]
+fn centered_markup_with_outline() {
+    let text = BitmapText {
+        text: "[color=#f80]Hot[/color] and [color=#0cf]fresh[/color] pizza, on the way!".into(),
+        align: TextAlign::Center,
+        outline: Some(Color::BLACK),
+        background_color: Color::rgb(0.1, 0.1, 0.2),
+        ..sample("GeeBee", "")
+    };
+    assert_golden("centered-markup-outline", text, &load_fonts());
+}